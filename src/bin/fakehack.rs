@@ -0,0 +1,296 @@
+// a stand-in for NetHack for the end to end tests: one small room drawn
+// the way the tty interface draws it, answering just the keys titm
+// sends, from character creation through to quitting
+//
+//     fakehack [--level empty|wand|wands] [--appearance oak] [--wand] [--levitating]
+//              [-p role] [-r race]
+//
+// the character starts on the up stairs; the "wand" level has a wand
+// far enough away that the bot has to travel to it and "wands" has two,
+// both of the one appearance; --wand starts the character off with a
+// wand the way a Wizard does and --levitating keeps anything from being
+// picked up
+use std::env;
+use std::io::{stdin, stdout, Read, Stdin, Write};
+use std::process;
+use termion::raw::IntoRawMode;
+
+const ROOM: [&str; 7] = [
+    "-------------",
+    "|...........|",
+    "|...........|",
+    "|...........|",
+    "|...........|",
+    "|...........|",
+    "-------------"
+];
+// where the room's top left corner goes on the map
+const ROOM_AT: (usize, usize) = (4, 20);
+// the up stairs, where the character starts
+const STAIRS: (usize, usize) = (ROOM_AT.0 + 1, ROOM_AT.1 + 2);
+
+struct Game {
+    player: (usize, usize),
+    wands: Vec<(usize, usize)>,
+    appearance: String,
+    inventory: Vec<String>,
+    levitating: bool,
+    turns: u32,
+    keys: Stdin
+}
+
+fn floor(y: usize, x: usize) -> bool {
+    let (top, left) = ROOM_AT;
+    if y < top || x < left {
+        return false;
+    }
+    ROOM.get(y - top).and_then(|row| row.chars().nth(x - left)) == Some('.')
+}
+
+fn direction(key: u8) -> Option<(i32, i32)> {
+    match key.to_ascii_lowercase() {
+        b'y' => Some((-1, -1)),
+        b'k' => Some((-1, 0)),
+        b'u' => Some((-1, 1)),
+        b'h' => Some((0, -1)),
+        b'l' => Some((0, 1)),
+        b'b' => Some((1, -1)),
+        b'j' => Some((1, 0)),
+        b'n' => Some((1, 1)),
+        _ => None
+    }
+}
+
+impl Game {
+    // anything going wrong with the terminal means titm hung up
+    fn key(&mut self) -> u8 {
+        let mut key = [0];
+        match self.keys.read_exact(&mut key) {
+            Ok(()) => key[0],
+            Err(_) => process::exit(1)
+        }
+    }
+
+    fn send(&self, text: &str) {
+        let mut out = stdout();
+        if out.write_all(text.as_bytes()).and_then(|_| out.flush()).is_err() {
+            process::exit(1);
+        }
+    }
+
+    // the whole screen, with the message last and the cursor put back
+    // on the player the way NetHack leaves it
+    fn draw(&self, message: &str) {
+        let mut out = String::from("\x1b[H\x1b[2J");
+        let (top, left) = ROOM_AT;
+        for (i, row) in ROOM.iter().enumerate() {
+            out.push_str(&format!("\x1b[{};{}H{}", top + i + 2, left + 1, row));
+        }
+        out.push_str(&format!("\x1b[{};{}H<", STAIRS.0 + 2, STAIRS.1 + 1));
+        for (y, x) in self.wands.iter() {
+            out.push_str(&format!("\x1b[{};{}H/", y + 2, x + 1));
+        }
+        let (y, x) = self.player;
+        out.push_str(&format!("\x1b[{};{}H@", y + 2, x + 1));
+        out.push_str("\x1b[23;1HAgent the Evoker          St:9 Dx:14 Co:12 In:18 Wi:11 Ch:9 Chaotic");
+        out.push_str(&format!("\x1b[24;1HDlvl:1 $:0 HP:12(12) Pw:7(7) AC:9 Xp:1/0 T:{}", self.turns));
+        if !message.is_empty() {
+            out.push_str(&format!("\x1b[1;1H\x1b[0;1m{}\x1b[0m", message));
+        }
+        out.push_str(&format!("\x1b[{};{}H", y + 2, x + 1));
+        self.send(&out);
+    }
+
+    // a prompt on the top line, with the cursor waiting after it
+    fn ask(&mut self, question: &str) -> u8 {
+        self.send(&format!("\x1b[1;1H\x1b[2K{} ", question));
+        self.key()
+    }
+
+    fn wand_name(&self) -> String {
+        let article = if self.appearance.starts_with(['a', 'e', 'i', 'o', 'u']) { "an" } else { "a" };
+        format!("{} {} wand", article, self.appearance)
+    }
+
+    // what's here, said on walking onto objects and when asked with ':'
+    // whether there's anything or not
+    fn look_here(&self, asked: bool) -> String {
+        let stairs = if self.player == STAIRS { "There is a staircase up here." } else { "" };
+        if self.wands.contains(&self.player) {
+            let sep = if stairs.is_empty() { "" } else { "  " };
+            return format!("{}{}You see here {}.", stairs, sep, self.wand_name());
+        }
+        match (asked, stairs) {
+            (false, _) => String::new(),
+            (true, "") => "You see no objects here.".to_string(),
+            (true, stairs) => stairs.to_string()
+        }
+    }
+
+    fn step(&mut self, (dy, dx): (i32, i32)) {
+        let (y, x) = ((self.player.0 as i32 + dy) as usize, (self.player.1 as i32 + dx) as usize);
+        if floor(y, x) {
+            self.player = (y, x);
+            self.turns += 1;
+        }
+        let message = self.look_here(false);
+        self.draw(&message);
+    }
+
+    // the travel command's position prompt, only the keys titm uses
+    fn travel(&mut self) {
+        self.send("\x1b[1;1H\x1b[2KWhere do you want to travel to?  (For instructions type a ?)");
+        let mut cursor = self.player;
+        loop {
+            self.send(&format!("\x1b[{};{}H", cursor.0 + 2, cursor.1 + 1));
+            let key = self.key();
+            match key {
+                b'@' => cursor = self.player,
+                b'.' | b',' | b';' | b':' => break,
+                0x1b => return self.draw(""),
+                _ => if let Some((dy, dx)) = direction(key) {
+                    let n = if key.is_ascii_uppercase() { 8 } else { 1 };
+                    cursor = ((cursor.0 as i32 + dy * n).max(0) as usize, (cursor.1 as i32 + dx * n).max(0) as usize);
+                }
+            }
+        }
+        if floor(cursor.0, cursor.1) {
+            self.player = cursor;
+            self.turns += 1;
+        }
+        let message = self.look_here(false);
+        self.draw(&message);
+    }
+
+    fn pick_up(&mut self) {
+        if self.levitating {
+            self.draw("You cannot reach the floor.");
+        } else if self.wands.contains(&self.player) {
+            let player = self.player;
+            self.wands.retain(|&wand| wand != player);
+            let item = format!("f - {}", self.wand_name());
+            self.inventory.push(item.clone());
+            self.draw(&format!("{}.", item));
+        } else {
+            self.draw("There is nothing here to pick up.");
+        }
+    }
+
+    fn show_inventory(&mut self) {
+        if self.inventory.is_empty() {
+            return self.draw("Not carrying anything.");
+        }
+        let mut lines = vec!["Wands".to_string()];
+        lines.extend(self.inventory.iter().cloned());
+        lines.push("(end)".to_string());
+        let mut out = String::new();
+        for (i, line) in lines.iter().enumerate() {
+            out.push_str(&format!("\x1b[{};50H\x1b[K{}", i + 1, line));
+        }
+        self.send(&out);
+        self.key();
+        self.draw("");
+    }
+
+    fn extended_command(&mut self) {
+        self.send("\x1b[1;1H\x1b[2K# ");
+        let mut command = String::new();
+        loop {
+            match self.key() {
+                b'\r' | b'\n' => break,
+                0x1b => return self.draw(""),
+                key => {
+                    command.push(key as char);
+                    self.send(&(key as char).to_string());
+                }
+            }
+        }
+        if command.trim() == "quit" {
+            if self.ask("Really quit? [yn] (n)") == b'y' {
+                self.game_over();
+            }
+            self.draw("");
+        } else {
+            self.draw(&format!("#{}: unknown extended command.", command.trim()));
+        }
+    }
+
+    // any answer but 'y' skips the disclosure, then one --More-- and out
+    fn game_over(&mut self) -> ! {
+        self.ask("Do you want your possessions identified? [ynq] (n)");
+        self.send("\x1b[H\x1b[2JGoodbye Agent the Evoker...--More--");
+        self.key();
+        self.send("\r\n");
+        process::exit(0);
+    }
+}
+
+fn main() {
+    let mut level = String::from("empty");
+    let mut appearance = String::from("oak");
+    let mut inventory = Vec::new();
+    let mut levitating = false;
+    let mut args = env::args().skip(1);
+    while let Some(arg) = args.next() {
+        match arg.as_str() {
+            "--level" => level = args.next().unwrap_or_default(),
+            "--appearance" => appearance = args.next().unwrap_or_default(),
+            "--wand" => inventory.push("a - a balsa wand".to_string()),
+            "--levitating" => levitating = true,
+            // role and race, there's only the one character on offer
+            "-p" | "-r" => { args.next(); },
+            _ => ()
+        }
+    }
+
+    let _raw = match stdout().into_raw_mode() {
+        Ok(raw) => raw,
+        Err(e) => {
+            eprintln!("fakehack needs a terminal: {}", e);
+            process::exit(2);
+        }
+    };
+    let mut game = Game {
+        player: STAIRS,
+        wands: match level.as_str() {
+            "wand" => vec![(ROOM_AT.0 + 5, ROOM_AT.1 + 10)],
+            "wands" => vec![(ROOM_AT.0 + 5, ROOM_AT.1 + 10), (ROOM_AT.0 + 2, ROOM_AT.1 + 7)],
+            _ => Vec::new()
+        },
+        appearance,
+        inventory,
+        levitating,
+        turns: 1,
+        keys: stdin()
+    };
+
+    game.send("\x1b[H\x1b[2J");
+    game.ask("Shall I pick character's race, role, gender and alignment for you? [ynaq]");
+    game.draw("Hello Agent, welcome to NetHack!  You are a chaotic male elven Wizard.");
+    loop {
+        let key = game.key();
+        match key {
+            b':' => {
+                let message = game.look_here(true);
+                game.draw(&message);
+            },
+            b',' => game.pick_up(),
+            b'i' => game.show_inventory(),
+            b'_' => game.travel(),
+            b'#' => game.extended_command(),
+            // going up from the first level ends the game like a quit
+            b'<' => if game.player != STAIRS {
+                game.draw("You can't go up here.");
+            } else if game.ask("Beware, there will be no return!  Still climb? [ynq] (n)") == b'y' {
+                game.game_over();
+            } else {
+                game.draw("");
+            },
+            0x1b | b'\r' | b' ' => game.draw(""),
+            _ => match direction(key) {
+                Some(step) => game.step(step),
+                None => game.draw("Unknown command.")
+            }
+        }
+    }
+}
@@ -0,0 +1,234 @@
+use std::error;
+use serde::Deserialize;
+use crate::menu::Menu;
+use crate::nethack::Align;
+
+type Result<T> = std::result::Result<T, Box<dyn error::Error>>;
+
+// the [character] table of the config file, e.g.
+//
+//     [character]
+//     role = "wizard"
+//     race = "elf"
+//     gender = "female"
+//     alignment = "chaotic"
+//
+// anything left out is left for NetHack to pick at random
+#[derive(Deserialize)]
+struct CharacterFile {
+    character: Option<CharacterSpec>
+}
+
+#[derive(Deserialize)]
+#[serde(deny_unknown_fields)]
+struct CharacterSpec {
+    role: Option<String>,
+    race: Option<String>,
+    gender: Option<String>,
+    alignment: Option<String>
+}
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Role {
+    Archeologist,
+    Barbarian,
+    Caveman,
+    Healer,
+    Knight,
+    Monk,
+    Priest,
+    Rogue,
+    Ranger,
+    Samurai,
+    Tourist,
+    Valkyrie,
+    Wizard
+}
+
+impl Role {
+    fn all() -> [Role; 13] {
+        [Role::Archeologist, Role::Barbarian, Role::Caveman, Role::Healer,
+         Role::Knight, Role::Monk, Role::Priest, Role::Rogue, Role::Ranger,
+         Role::Samurai, Role::Tourist, Role::Valkyrie, Role::Wizard]
+    }
+
+    // male name first, the role menu shows the female one for
+    // female characters
+    fn names(self) -> &'static [&'static str] {
+        match self {
+            Role::Archeologist => &["archeologist"],
+            Role::Barbarian => &["barbarian"],
+            Role::Caveman => &["caveman", "cavewoman"],
+            Role::Healer => &["healer"],
+            Role::Knight => &["knight"],
+            Role::Monk => &["monk"],
+            Role::Priest => &["priest", "priestess"],
+            Role::Rogue => &["rogue"],
+            Role::Ranger => &["ranger"],
+            Role::Samurai => &["samurai"],
+            Role::Tourist => &["tourist"],
+            Role::Valkyrie => &["valkyrie"],
+            Role::Wizard => &["wizard"]
+        }
+    }
+
+    // the rank titles a new character starts out with
+    fn first_ranks(self) -> &'static [&'static str] {
+        match self {
+            Role::Archeologist => &["Digger"],
+            Role::Barbarian => &["Plunderer", "Plunderess"],
+            Role::Caveman => &["Troglodyte"],
+            Role::Healer => &["Rhizotomist"],
+            Role::Knight => &["Gallant"],
+            Role::Monk => &["Candidate"],
+            Role::Priest => &["Aspirant"],
+            Role::Rogue => &["Footpad"],
+            Role::Ranger => &["Tenderfoot"],
+            Role::Samurai => &["Hatamoto"],
+            Role::Tourist => &["Rambler"],
+            Role::Valkyrie => &["Stripling"],
+            Role::Wizard => &["Evoker"]
+        }
+    }
+
+    fn from_rank(rank: &str) -> Option<Self> {
+        Role::all().iter().copied().find(|role| role.first_ranks().contains(&rank))
+    }
+
+    // takes the full name or the three letter one NetHack's options use
+    pub fn from_name(name: &str) -> Option<Self> {
+        let name = name.to_lowercase();
+        Role::all().iter().copied().find(|role| {
+            role.names().iter().any(|n| *n == name || (name.len() == 3 && n.starts_with(name.as_str())))
+        })
+    }
+}
+
+const RACES: [&str; 5] = ["human", "elf", "dwarf", "gnome", "orc"];
+const GENDERS: [&str; 2] = ["male", "female"];
+
+fn align_name(align: Align) -> &'static str {
+    match align {
+        Align::Lawful => "lawful",
+        Align::Neutral => "neutral",
+        Align::Chaotic => "chaotic",
+        Align::Unaligned => "unaligned"
+    }
+}
+
+// e.g. "wizard-chaotic" from the statusline of a new character, for
+// file names; rank titles past the first just get used as they are
+pub fn label(rank: &str, align: Align) -> String {
+    let role = match Role::from_rank(rank) {
+        Some(role) => role.names()[0].to_string(),
+        None => rank.to_lowercase()
+    };
+    format!("{}-{}", role, align_name(align))
+}
+
+// "an Archeologist" -> "archeologist"
+fn entry_name(text: &str) -> String {
+    let text = text.to_lowercase();
+    let text = text.strip_prefix("a ").or_else(|| text.strip_prefix("an ")).unwrap_or(&text);
+    text.trim().to_string()
+}
+
+#[derive(Default)]
+pub struct Character {
+    role: Option<Role>,
+    race: Option<&'static str>,
+    gender: Option<&'static str>,
+    align: Option<Align>
+}
+
+impl Character {
+    pub fn parse(text: &str) -> Result<Self> {
+        let file: CharacterFile = toml::from_str(text)?;
+        let spec = match file.character {
+            Some(spec) => spec,
+            None => return Ok(Character::default())
+        };
+        let role = match spec.role {
+            Some(ref r) => Some(Role::from_name(r)
+                .ok_or(format!("unknown role in character: {}", r))?),
+            None => None
+        };
+        let race = match spec.race {
+            Some(ref r) => Some(*RACES.iter().find(|race| **race == r.to_lowercase())
+                .ok_or(format!("unknown race in character: {}", r))?),
+            None => None
+        };
+        let gender = match spec.gender {
+            Some(ref g) => Some(*GENDERS.iter().find(|gender| **gender == g.to_lowercase())
+                .ok_or(format!("unknown gender in character: {}", g))?),
+            None => None
+        };
+        let align = match spec.alignment {
+            Some(ref a) => match Align::from_name(a) {
+                Some(Align::Unaligned) | None => return Err(format!("unknown alignment in character: {}", a).into()),
+                align => align
+            },
+            None => None
+        };
+        Ok(Character { role, race, gender, align })
+    }
+
+    // role and race can be settled on the command line, NetHack only
+    // takes gender and alignment from its options
+    pub fn args(&self) -> Vec<String> {
+        let mut args = Vec::new();
+        if let Some(role) = self.role {
+            args.push("-p".to_string());
+            args.push(role.names()[0].to_string());
+        }
+        if let Some(race) = self.race {
+            args.push("-r".to_string());
+            args.push(race.to_string());
+        }
+        args
+    }
+
+    // answer to "Shall I pick ... for you?", saying no brings up menus
+    // for whatever the command line didn't settle
+    pub fn pick_answer(&self) -> &'static str {
+        if self.gender.is_none() && self.align.is_none() { "y" } else { "n" }
+    }
+
+    // the letter to pick in one of the character creation menus, which
+    // all end with a "Random" entry, or None for any other menu
+    pub fn menu_key(&self, menu: &Menu) -> Option<char> {
+        if let Some(entry) = menu.entries.iter().find(|e| entry_name(&e.text).starts_with("yes; start game")) {
+            return Some(entry.letter);
+        }
+        let random = menu.entries.iter().find(|e| entry_name(&e.text) == "random")?;
+
+        let mut wanted: Vec<&str> = Vec::new();
+        if let Some(role) = self.role {
+            wanted.extend(role.names());
+        }
+        wanted.extend(self.race);
+        wanted.extend(self.gender);
+        wanted.extend(self.align.map(align_name));
+        let entry = menu.entries.iter()
+            .find(|e| wanted.contains(&entry_name(&e.text).as_str()))
+            .unwrap_or(random);
+        Some(entry.letter)
+    }
+
+    // whether the game that started is the one asked for, going by the
+    // first statusline
+    pub fn check(&self, rank: &str, align: Align) -> Result<()> {
+        if let Some(role) = self.role {
+            if !role.first_ranks().contains(&rank) {
+                return Err(format!("asked for a {} but the statusline says {}", role.names()[0], rank).into());
+            }
+        }
+        if let Some(wanted) = self.align {
+            if wanted != align {
+                return Err(format!("asked for a {} character but the statusline says {}",
+                                   align_name(wanted), align_name(align)).into());
+            }
+        }
+        Ok(())
+    }
+}
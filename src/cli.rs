@@ -0,0 +1,85 @@
+use std::path::PathBuf;
+use clap::{Parser, Subcommand};
+
+#[derive(Parser)]
+#[command(name = "titm", about = "savescums NetHack until it finds the items it's told to look for")]
+pub struct Cli {
+    #[arg(short, long, global = true, value_name = "FILE",
+          help = "config file with the [[target]] rules and the [character] and [game] tables")]
+    pub config: Option<PathBuf>,
+
+    #[arg(short, long, global = true, value_name = "COMMAND",
+          help = "game binary to run, overriding the config file")]
+    pub game: Option<String>,
+
+    #[arg(short, long, global = true, value_name = "FILE",
+          help = "append a log of what the bot saw and did to FILE instead of stderr")]
+    pub log: Option<PathBuf>,
+
+    #[arg(short, long, global = true, action = clap::ArgAction::Count,
+          help = "log every frame's state, look, action and keys sent, twice for the screen's windows too; to titm.log unless --log says otherwise")]
+    pub verbose: u8,
+
+    #[arg(long, global = true, value_name = "FILE", default_value = "titm-runs.log",
+          help = "where the outcome of every game is recorded")]
+    pub runs: PathBuf,
+
+    #[command(subcommand)]
+    pub mode: Mode
+}
+
+#[derive(Subcommand)]
+pub enum Mode {
+    #[command(about = "play games until a target turns up")]
+    Run {
+        #[arg(long, value_name = "N", help = "give up after N games")]
+        max_runs: Option<u32>,
+        #[arg(long, help = "run without a terminal, showing nothing and taking no keys")]
+        headless: bool,
+        #[arg(long, value_name = "COLSxROWS", default_value = "80x24", value_parser = parse_size,
+              help = "screen size for the game when headless")]
+        size: (u16, u16),
+        #[arg(long, value_name = "DIR", default_value = "ttyrec",
+              help = "directory to keep a ttyrec of every game in")]
+        record: PathBuf,
+        #[arg(long, help = "don't record the games")]
+        no_record: bool
+    },
+
+    #[command(about = "run the game with no bot, just passing keys through")]
+    Watch,
+
+    #[command(about = "play back a ttyrec recording")]
+    Replay {
+        ttyrec: PathBuf,
+        #[arg(long, default_value_t = 1.0, help = "playback speed multiplier")]
+        speed: f64,
+        #[arg(long, help = "print what the bot would send at each frame instead of playing it back")]
+        bot: bool,
+        #[arg(long, value_name = "COLSxROWS", default_value = "80x24", value_parser = parse_size,
+              help = "screen size the game was recorded at, for --bot")]
+        size: (u16, u16)
+    },
+
+    #[command(about = "show what the parsers make of a captured screen")]
+    Parse {
+        file: PathBuf,
+        #[arg(long, value_name = "COLSxROWS", default_value = "80x24", value_parser = parse_size,
+              help = "screen size the capture was made at")]
+        size: (u16, u16)
+    },
+
+    #[command(about = "summarise the games recorded so far")]
+    Stats
+}
+
+// "80x24" -> (80, 24)
+fn parse_size(text: &str) -> Result<(u16, u16), String> {
+    let (cols, rows) = text.split_once('x').ok_or("size should look like 80x24")?;
+    let cols = cols.parse().map_err(|_| format!("bad column count: {}", cols))?;
+    let rows = rows.parse().map_err(|_| format!("bad row count: {}", rows))?;
+    if cols == 0 || rows == 0 {
+        return Err("size can't be zero".to_string());
+    }
+    Ok((cols, rows))
+}
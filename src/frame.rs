@@ -0,0 +1,174 @@
+use std::io::{Error as ioErr, Result as ioResult};
+use std::os::unix::io::{AsRawFd, RawFd};
+use std::time::{Duration, Instant};
+use nix::poll::{poll, PollFd, PollFlags};
+use nix::errno::Errno;
+use nix::unistd;
+use nix::sys::signal::Signal;
+use nix::sys::wait::WaitStatus;
+use crate::screen::GameScreen;
+use crate::signals;
+use crate::term::{PtyError, PtyReader};
+
+pub enum Event {
+    Byte(u8),
+    // whatever the user typed since the last event
+    Input(Vec<u8>),
+    // the user's terminal changed size
+    Resize,
+    // the game's gone, going by SIGCHLD or the pty failing with EIO
+    Exited(WaitStatus),
+    // the deadline from set_timer() passed
+    Timer,
+    ScreenStable,
+    Eof
+}
+
+enum Ready {
+    Pty,
+    Quiet,
+    Input(Vec<u8>),
+    // a signal arrived while waiting
+    Interrupted
+}
+
+// NetHack can show the cursor half way through a redraw, and a single
+// frame can arrive over several reads, so instead of reacting to the
+// show-cursor escape we wait until the pty has been quiet for a while
+// and the emulator says the cursor is visible
+pub struct FrameDetector {
+    quiet: Duration,
+    stall: Duration,
+    dirty: bool,
+    last_byte: Instant,
+    input: Option<RawFd>,
+    deadline: Option<Instant>,
+    // SIGCHLD came in, what's left in the pty is the last of the game
+    exited: bool
+}
+
+impl FrameDetector {
+    pub fn new(quiet: Duration, stall: Duration) -> Self {
+        FrameDetector {
+            quiet,
+            stall,
+            dirty: false,
+            last_byte: Instant::now(),
+            input: None,
+            deadline: None,
+            exited: false
+        }
+    }
+
+    // one Timer event after `after`, replacing any earlier timer
+    pub fn set_timer(&mut self, after: Duration) {
+        self.deadline = Some(Instant::now() + after);
+    }
+
+    pub fn cancel_timer(&mut self) {
+        self.deadline = None;
+    }
+
+    // also wait on the user's keyboard, read straight off the fd so
+    // nothing sits in a buffer poll() can't see
+    pub fn with_input(mut self, fd: RawFd) -> Self {
+        self.input = Some(fd);
+        self
+    }
+
+    fn wait(&mut self, reader: &PtyReader, timeout: Option<Duration>) -> ioResult<Ready> {
+        if reader.len() > 0 {
+            return Ok(Ready::Pty);
+        }
+        let mut fds = vec![PollFd::new(reader.as_raw_fd(), PollFlags::POLLIN)];
+        if let Some(fd) = self.input {
+            fds.push(PollFd::new(fd, PollFlags::POLLIN));
+        }
+        let millis = timeout.map_or(-1, |t| t.as_millis() as i32);
+        match poll(&mut fds, millis) {
+            Ok(_) => (),
+            Err(nix::Error::Sys(Errno::EINTR)) => return Ok(Ready::Interrupted),
+            Err(e) => return Err(ioErr::other(e))
+        }
+
+        let ready = |fd: &PollFd| fd.revents().is_some_and(|r| !r.is_empty());
+        if let (Some(input), true) = (self.input, fds.len() > 1 && ready(&fds[1])) {
+            let mut buf = [0u8; 256];
+            let n = unistd::read(input, &mut buf).map_err(ioErr::other)?;
+            if n == 0 {
+                // the user's end closed, stop listening to it
+                self.input = None;
+            }
+            return Ok(Ready::Input(buf[..n].to_vec()));
+        }
+        Ok(if ready(&fds[0]) { Ready::Pty } else { Ready::Quiet })
+    }
+
+    // have the screen as it stands reported as stable again, for when
+    // something other than the game wants a look at it
+    pub fn refresh(&mut self) {
+        self.dirty = true;
+    }
+
+    fn read_byte(&mut self, reader: &mut PtyReader) -> ioResult<Event> {
+        match reader.next_byte() {
+            Ok(Some(c)) => {
+                self.dirty = true;
+                self.last_byte = Instant::now();
+                Ok(Event::Byte(c))
+            },
+            Ok(None) => Ok(Event::Eof),
+            Err(PtyError::ChildExited(status)) => Ok(Event::Exited(status)),
+            Err(e) => Err(ioErr::other(e))
+        }
+    }
+
+    pub fn next_event(&mut self, reader: &mut PtyReader, screen: &GameScreen) -> ioResult<Event> {
+        loop {
+            if signals::take(Signal::SIGWINCH) {
+                return Ok(Event::Resize);
+            }
+            if signals::take(Signal::SIGCHLD) {
+                self.exited = true;
+            }
+            // the game's last words still go to the screen and the
+            // recording before it's reported gone
+            if self.exited {
+                match self.wait(reader, Some(Duration::ZERO))? {
+                    Ready::Input(keys) => return Ok(Event::Input(keys)),
+                    Ready::Pty => return self.read_byte(reader),
+                    Ready::Interrupted => continue,
+                    Ready::Quiet => return Ok(Event::Exited(reader.reap().map_err(ioErr::other)?))
+                }
+            }
+            let now = Instant::now();
+            if self.deadline.is_some_and(|deadline| deadline <= now) {
+                self.deadline = None;
+                return Ok(Event::Timer);
+            }
+
+            // with nothing new on screen since the last stable event
+            // there's only the timer to wake up for
+            let timeout = match (self.dirty, self.deadline) {
+                (true, Some(deadline)) => Some(self.quiet.min(deadline - now)),
+                (true, None) => Some(self.quiet),
+                (false, deadline) => deadline.map(|deadline| deadline - now)
+            };
+            match self.wait(reader, timeout)? {
+                Ready::Input(keys) => return Ok(Event::Input(keys)),
+                Ready::Interrupted => continue,
+                // woken early for the timer
+                Ready::Quiet if !self.dirty || self.last_byte.elapsed() < self.quiet => continue,
+                Ready::Quiet => (),
+                Ready::Pty => return self.read_byte(reader)
+            }
+
+            // a hidden cursor means the game is still drawing, but don't
+            // wait on it forever in case it never turns the cursor back on
+            if screen.cursor_visible() || self.last_byte.elapsed() >= self.stall {
+                self.dirty = false;
+                return Ok(Event::ScreenStable);
+            }
+        }
+    }
+}
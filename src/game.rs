@@ -0,0 +1,98 @@
+use std::error;
+use std::fs;
+use std::path::PathBuf;
+use std::process::Command;
+use serde::Deserialize;
+
+type Result<T> = std::result::Result<T, Box<dyn error::Error>>;
+
+// the [game] table of the config file says how to start NetHack, e.g.
+//
+//     [game]
+//     command = "/usr/games/nethack-3.7"
+//     args = ["-u", "titm"]
+//     nethackoptions = "OPTIONS=!autopickup,!legacy"
+//     home = "/tmp/titm-home"
+//     hackdir = "/usr/lib/nethack-3.7"
+//     playground = "/tmp/titm-3.7"
+//
+// everything is optional, with nothing set it's just `nethack` run
+// from the current directory with the environment titm was given
+#[derive(Deserialize)]
+struct GameFile {
+    game: Option<GameConfig>
+}
+
+#[derive(Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct GameConfig {
+    #[serde(default = "default_command")]
+    command: String,
+    #[serde(default)]
+    args: Vec<String>,
+    nethackoptions: Option<String>,
+    home: Option<PathBuf>,
+    hackdir: Option<PathBuf>,
+    // directory the game is started in, created if it isn't there
+    playground: Option<PathBuf>
+}
+
+fn default_command() -> String {
+    "nethack".to_string()
+}
+
+impl GameConfig {
+    pub fn parse(text: &str) -> Result<Self> {
+        let file: GameFile = toml::from_str(text)?;
+        Ok(file.game.unwrap_or_default())
+    }
+
+    // makes sure the directories the game is pointed at exist, so a
+    // fresh playground doesn't fail on every restart
+    pub fn prepare(&self) -> Result<()> {
+        for dir in [&self.playground, &self.home].iter().copied().flatten() {
+            fs::create_dir_all(dir)
+                .map_err(|e| format!("could not create {}: {}", dir.display(), e))?;
+        }
+        Ok(())
+    }
+
+    pub fn command(&self, extra_args: &[String]) -> Command {
+        let mut command = Command::new(&self.command);
+        command.args(&self.args).args(extra_args);
+        if let Some(ref options) = self.nethackoptions {
+            command.env("NETHACKOPTIONS", options);
+        }
+        if let Some(ref home) = self.home {
+            command.env("HOME", home);
+        }
+        if let Some(ref hackdir) = self.hackdir {
+            command.env("HACKDIR", hackdir);
+        }
+        if let Some(ref playground) = self.playground {
+            command.current_dir(playground);
+        }
+        command
+    }
+
+    pub fn set_command(&mut self, command: &str) {
+        self.command = command.to_string();
+    }
+
+    pub fn name(&self) -> &str {
+        &self.command
+    }
+}
+
+impl Default for GameConfig {
+    fn default() -> Self {
+        GameConfig {
+            command: default_command(),
+            args: Vec::new(),
+            nethackoptions: None,
+            home: None,
+            hackdir: None,
+            playground: None
+        }
+    }
+}
@@ -0,0 +1,40 @@
+use std::error;
+use std::io::{self, Write};
+use std::time::{SystemTime, UNIX_EPOCH};
+use log::{LevelFilter, Log, Metadata, Record};
+
+type Result<T> = std::result::Result<T, Box<dyn error::Error>>;
+
+// one line of key=value fields per record on stderr, which main points
+// at the log file so none of it ends up on the game's screen:
+//
+//     time=1792263889.132 level=INFO target=titm event=start game=1
+struct Logger;
+
+impl Log for Logger {
+    fn enabled(&self, metadata: &Metadata) -> bool {
+        metadata.level() <= log::max_level()
+    }
+
+    fn log(&self, record: &Record) {
+        if !self.enabled(record.metadata()) {
+            return;
+        }
+        let now = SystemTime::now().duration_since(UNIX_EPOCH).unwrap_or_default();
+        let mut stderr = io::stderr().lock();
+        let _ = writeln!(stderr, "time={}.{:03} level={} target={} {}",
+                         now.as_secs(), now.subsec_millis(), record.level(), record.target(), record.args());
+    }
+
+    fn flush(&self) {
+        let _ = io::stderr().flush();
+    }
+}
+
+static LOGGER: Logger = Logger;
+
+pub fn init(level: LevelFilter) -> Result<()> {
+    log::set_logger(&LOGGER)?;
+    log::set_max_level(level);
+    Ok(())
+}
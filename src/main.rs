@@ -1,63 +1,101 @@
+mod character;
+mod cli;
+mod frame;
+mod game;
+mod logger;
+mod map;
+mod menu;
+mod nethack;
+mod objects;
+mod overlay;
+mod path;
+mod rules;
+mod runs;
+mod screen;
+mod signals;
+#[cfg(test)]
+mod snapshots;
 mod term;
-use crate::term::{fork_terminal, TermFork};
+mod ttyrec;
+use crate::character::Character;
+use crate::cli::{Cli, Mode};
+use crate::frame::{Event, FrameDetector};
+use crate::game::GameConfig;
+use crate::menu::read_menu;
+use crate::map::{MapCell, NHMap, Terrain};
+use crate::nethack::{ItemClass, NetHackData, NHInvItem};
+use crate::objects::parse_object;
+use crate::path::{find_path, path_keys, travel_keys};
+use crate::rules::TargetRules;
+use crate::runs::{Outcome, RunRecord};
+use crate::screen::{row_text, GameScreen};
+use crate::term::{fork_terminal, get_winsize, sizeinfo_from, virtual_winsize, PtyReader, PtyWriter, TermFork};
+use crate::ttyrec::TtyrecReader;
+use std::collections::HashSet;
 use std::error;
+use log::{debug, info, warn, LevelFilter};
+use std::fs::{self, File, OpenOptions};
+use std::path::{Path, PathBuf};
 use std::str;
-use std::i32::MAX;
-use std::f64::MAX as MAX_FLOAT;
-use std::io::{stdout, Write};
-use std::process::Command;
-use regex::CaptureLocations;
-use terminal_emulator::ansi::Processor;
-use terminal_emulator::term::Term;
+use std::io::{self, stdin, stdout, BufReader, Read, Write};
+use std::os::unix::io::AsRawFd;
+use std::process;
+use std::thread;
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+use clap::Parser;
+use nix::errno::Errno;
+use nix::poll::{poll, PollFd, PollFlags};
+use nix::sys::signal::Signal;
+use nix::sys::wait::WaitStatus;
+use nix::unistd::dup2;
 use termion::raw::IntoRawMode;
 use regex::Regex;
 
 type Result<T> = std::result::Result<T, Box<dyn error::Error>>;
 
-fn get_box(term: &Term) -> (i32, i32, i32, i32) {
-    let (cursor_posy, cursor_posx) = (*term.cursor().point.line as i32, *term.cursor().point.col as i32);
-    let (mut north, mut east, mut south, mut west) = (MAX, -MAX, -MAX, MAX);
-    let grid = term.grid();
-    for cell in grid.display_iter() {
-        let (disty, distx) = (cursor_posy - *cell.line as i32, cursor_posx - *cell.column as i32);
-        match cell.c {
-            '─' if disty > 0 && disty < north => north = disty,
-            '─' if disty < 0 && disty > south => south = disty,
-            '│' if distx < 0 && distx > east => east = distx,
-            '│' if distx > 0 && distx < west  => west = distx,
-            _ => ()
-        }
-    }
-    (north, south, east, west)
-}
+// paths at least this long are left to NetHack's travel command
+// instead of being walked a step per frame
+const TRAVEL_MIN: usize = 3;
 
-fn calculate_distance(disty: i32, distx: i32) -> f64 {
-    (f64::from(disty).powf(2.0) + f64::from(distx).powf(2.0)).sqrt()
-}
-
-const DIRECTION_GRID: [[&'static str; 3]; 3] = [["y","k","u"],["h",".","l"],["b","j","n"]];
+// tries at getting a door open, the first by opening and the rest by
+// kicking, before the game is given up on
+const DOOR_TRIES: u32 = 6;
 
-fn get_direction_key(dx: i32, dy: i32) -> &'static str {
-    let (x, y) = ((dx/dx.abs())+1, (dy/dy.abs())+1);
-    DIRECTION_GRID[y as usize][x as usize]
+#[derive(Debug)]
+enum Move {
+    // a single step, and whether it lands on the target
+    Step(char, bool),
+    // the travel command keys, where they should leave the player and
+    // whether that's on the target
+    Travel(String, (usize, usize), bool),
+    // the direction of a closed door next to the player, and where it is
+    Door(char, (usize, usize))
 }
 
-fn get_wand_vector(term: &Term) -> Option<(i32, i32)> {
-    let (cursor_posy, cursor_posx) = (*term.cursor().point.line as i32, *term.cursor().point.col as i32);
-    let mut distance = MAX_FLOAT;
-    let mut result = None;
-    let grid = term.grid();
-    for cell in grid.display_iter() {
-        if cell.c == '/' {
-            let (dy, dx) = (cursor_posy - *cell.line as i32, cursor_posx - *cell.column as i32);
-            let d = calculate_distance(dy,dx);
-            if d < distance {
-                distance = d;
-                result = Some((dy, dx));
-            }
-        }
+// how to get along the shortest path to the nearest reachable target,
+// leaving out the squares that have been looked at already
+fn walk_to_target(map: &NHMap, classes: &[ItemClass], inspected: &HashSet<(usize, usize)>) -> Option<Move> {
+    let from = map.player()?;
+    let mut path = find_path(map, from, |y, x| {
+        !inspected.contains(&(y, x))
+            && matches!(map.cell(y, x), MapCell::Item(class) if classes.contains(&class))
+    })?;
+    // only as far as the first closed door, which gets opened from the
+    // square in front of it
+    let door = path.iter().position(|&(y, x)| map.terrain(y, x) == Terrain::ClosedDoor);
+    if door == Some(0) {
+        return Some(Move::Door(path_keys(from, &path[..1]).chars().next()?, path[0]));
+    }
+    if let Some(door) = door {
+        path.truncate(door);
+    }
+    let arriving = door.is_none();
+    let dest = *path.last()?;
+    if path.len() >= TRAVEL_MIN {
+        return Some(Move::Travel(travel_keys(from, dest), dest, arriving));
     }
-    result
+    let keys = path_keys(from, &path);
+    Some(Move::Step(keys.chars().next()?, arriving && path.len() == 1))
 }
 
 fn shift(buf: &mut [u8]) {
@@ -66,148 +104,819 @@ fn shift(buf: &mut [u8]) {
     }
 }
 
-enum Item<'a> {
-    Wand(&'a str),
-    Strange(&'a str)
-}
-
-enum LookFeet<'a> {
+#[derive(Debug)]
+enum LookFeet {
     Nothing,
     UpStairs,
     DownStairs,
-    Loot(Item<'a>)
-}
-
-fn get_token<'a> (locs: &CaptureLocations, s: &'a str, i: usize) -> &'a str {
-    let (b, l) = locs.get(i).unwrap();
-    &s[b..l]
-}
-
-fn get_token_opt<'a> (locs: &CaptureLocations, s: &'a str, i: usize) -> Option<&'a str> {
-    if let Some((b, l)) = locs.get(i) {
-        Some(&s[b..l])
-    } else {
-        None
-    } 
-}
-
-fn parse_look_message<'a> (buf: &'a [u8]) -> Option<LookFeet<'a>> {
-    for bytes in buf.rsplitn(10, |c| *c == b'\x1b')
-        .find(|s| s.len() >= 5 && &s[0..5] == "[0;1m".as_bytes()) {
-        let no_objects_re = Regex::new(
-            r"You see no objects here\."
-        ).unwrap();
-        let s = str::from_utf8(bytes).unwrap();
-        
-        if no_objects_re.is_match(s) {
-            return Some(LookFeet::Nothing);
-        }
-        let re = Regex::new(
-            r"(?x)
-            (You\ssee\shere|There\sis)\s
-            (an?|\d+)\s
-            (?:(blessed|cursed|uncursed|holy|unholy)\s)?
-            (?:([[:^space:]]+)\s)*
-            (?:(of)\s)?
-            ([[:^space:]]+)
-            (?:\s(named|called)
-                \s([[:^space:]]+))?
-            (?:\s\(
-                (?P<C>\d+)
-                : (?P<c>\d+)
-            \))?\."
-        ).unwrap();
-        let mut locs = re.capture_locations();
-        let first_match = re.captures_read(&mut locs, s);
-        if first_match.is_none() { return None; }
-        if get_token(&locs, s, 0) == "There is a staircase up here." {
-            return Some(LookFeet::UpStairs);
-        }
-        if get_token(&locs, s, 0) == "There is a staircase down here." {
-            return Some(LookFeet::DownStairs);
-        }
-        if get_token(&locs, s, 1) == "You see here" {
-            let (item_type, item_description) = if let Some(_) = locs.get(5) {
-                (get_token(&locs, s, 4), get_token_opt(&locs, s, 6).unwrap_or(""))
-            } else {
-                (get_token(&locs, s, 6), get_token_opt(&locs, s, 4).unwrap_or(""))
-            };
-            if item_type == "wand" {
-                return Some(LookFeet::Loot(Item::Wand(item_description)));
-            }
-            return Some(LookFeet::Loot(Item::Strange(&s[13..])));
+    Loot(NHInvItem, bool),
+    Pile(Vec<NHInvItem>, bool)
+}
+
+// with more than one object on the square NetHack lists them in a
+// window instead of the message line, optionally with the dungeon
+// feature above the list:
+//
+//     There is a staircase up here.
+//
+//     Things that are here:
+//     an oak wand
+//     2 gray stones
+//     --More--
+fn parse_pile_window(rows: &[Vec<char>]) -> Option<LookFeet> {
+    let header: Vec<char> = "Things that are here:".chars().collect();
+    let (line, col) = rows.iter().enumerate().find_map(|(line, row)| {
+        row.windows(header.len()).position(|w| w == header.as_slice()).map(|col| (line, col))
+    })?;
+    let upstairs = rows[..line].iter()
+        .any(|row| row_text(row, col).starts_with("There is a staircase up here"));
+
+    let mut items = Vec::new();
+    for row in rows[line + 1 ..].iter() {
+        let text = row_text(row, col);
+        if text.is_empty() {
+            continue;
+        }
+        if text.starts_with("--More--") || text.starts_with(['(', '─', '└']) {
+            break;
+        }
+        match parse_object(&text) {
+            Some(item) => items.push(item),
+            None => break
         }
     }
-    None
+    Some(LookFeet::Pile(items, upstairs))
+}
+
+fn parse_look_message(buf: &[u8]) -> Option<LookFeet> {
+    let bytes = buf.rsplitn(10, |c| *c == b'\x1b')
+        .find(|s| s.len() >= 5 && &s[0..5] == "[0;1m".as_bytes())?;
+    let s = str::from_utf8(bytes).ok()?;
+    if s.contains("You see no objects here.") {
+        return Some(LookFeet::Nothing);
+    }
+
+    // the dungeon feature and the object are sentences of their own,
+    // "There is a staircase up here.  You see here an oak wand."
+    let feature = Regex::new(r"There is (.+?) here\.").unwrap().captures(s);
+    let upstairs = feature.as_ref().is_some_and(|caps| &caps[1] == "a staircase up");
+    if let Some(caps) = Regex::new(r"You see here (.+)\.").unwrap().captures(s) {
+        return parse_object(&caps[1]).map(|item| LookFeet::Loot(item, upstairs));
+    }
+    // a feature on its own is a finished look with nothing to pick up
+    match feature.as_ref().map(|caps| &caps[1]) {
+        Some("a staircase up") => Some(LookFeet::UpStairs),
+        Some("a staircase down") => Some(LookFeet::DownStairs),
+        Some(_) => Some(LookFeet::Nothing),
+        None => None
+    }
 }
 
 fn quit_string(stairs: bool) -> &'static str {
     if stairs { "<y   " } else { "# quit\ny   " }
 }
 
-fn respond(term: &Term, read_buf: &[u8], have_picked: &mut bool, have_looked: &mut bool, stairs: &mut bool) -> Option<&'static str> {
-    let (north, south, east, west) = get_box(term);
-    if let Some(feature) = parse_look_message(&read_buf[read_buf.len() - 512 ..]) {
+// questions NetHack asks while a game starts up or winds down, matched
+// against the line the cursor is waiting on
+const GAME_PROMPTS: [(&str, &str); 5] = [
+    ("Destroy old game?", "y"),
+    ("Is this ok?", "y"),
+    ("Really quit?", "y"),
+    ("Still climb?", "y"),
+    // 'q' skips the rest of the end of game disclosure questions too
+    ("Do you want your possessions identified?", "q")
+];
+
+fn answer_prompt(line: &str, character: &Character) -> Option<&'static str> {
+    let re = Regex::new(r"\[[a-zA-Z$-]+(?: or \?\*)?\](?: \(\w\))?\s*$").unwrap();
+    if !re.is_match(line) {
+        return None;
+    }
+    if line.contains("Shall I pick") {
+        return Some(character.pick_answer());
+    }
+    GAME_PROMPTS.iter()
+        .find(|(prompt, _)| line.contains(prompt))
+        .map(|(_, answer)| *answer)
+}
+
+#[derive(Debug)]
+struct BotState {
+    have_looked: bool,
+    stairs: bool,
+    // a pickup was sent but the inventory hasn't been checked since
+    pickup_pending: bool,
+    checking_inventory: bool,
+    // the wanted entries in the inventory, letter and quantity, as of
+    // the last check, None until the first one
+    carried: Option<Vec<(Option<char>, u32)>>,
+    // a target is confirmed to be in the inventory
+    have_picked: bool,
+    // where the last travel command should have taken the player, if
+    // that's onto a target
+    travelling: Option<(usize, usize)>,
+    // the closed door being got through and how many tries it's had
+    door: Option<((usize, usize), u32)>,
+    // squares on this level whose objects were looked at and turned
+    // down, and the level they're on
+    inspected: HashSet<(usize, usize)>,
+    dlvl: Option<u32>,
+    // the quit keys were sent, all that's left is to get through the
+    // end of game screens
+    quitting: bool
+}
+
+impl BotState {
+    fn new() -> Self {
+        BotState {
+            have_looked: false,
+            stairs: false,
+            pickup_pending: false,
+            checking_inventory: false,
+            carried: None,
+            have_picked: false,
+            travelling: None,
+            door: None,
+            inspected: HashSet::new(),
+            dlvl: None,
+            quitting: false
+        }
+    }
+}
+
+// what respond made of a frame, for the log: what the look found if
+// there was one, a name for what it's doing and the keys that do it
+struct Decision {
+    look: Option<String>,
+    action: &'static str,
+    keys: String
+}
+
+impl Decision {
+    fn new(action: &'static str, keys: &str) -> Self {
+        Decision { look: None, action, keys: keys.to_string() }
+    }
+
+    fn with_look(mut self, look: &Option<String>) -> Self {
+        self.look = look.clone();
+        self
+    }
+}
+
+fn look_summary(look: &LookFeet) -> String {
+    match look {
+        LookFeet::Loot(item, true) => format!("loot {} on the up stairs", item),
+        LookFeet::Loot(item, false) => format!("loot {}", item),
+        LookFeet::Pile(items, true) => format!("pile of {} on the up stairs", items.len()),
+        LookFeet::Pile(items, false) => format!("pile of {}", items.len()),
+        LookFeet::UpStairs => "up stairs".to_string(),
+        LookFeet::DownStairs => "down stairs".to_string(),
+        LookFeet::Nothing => "nothing".to_string()
+    }
+}
+
+fn respond(screen: &GameScreen, game: &mut NetHackData, rules: &TargetRules, character: &Character, read_buf: &[u8], state: &mut BotState) -> Decision {
+    let rows = screen.get_rows();
+    let menu = read_menu(&rows);
+    let (cursor_line, _) = screen.get_cursor();
+    let prompt = rows.get(cursor_line).map_or(String::new(), |row| row_text(row, 0));
+    let more = rows.iter().any(|row| row_text(row, 0).contains("--More--"));
+
+    if let Some(answer) = answer_prompt(&prompt, character) {
+        return Decision::new("answer-prompt", answer);
+    }
+    // the rest of the end of game questions, attributes, conduct and
+    // the like, are all skipped with 'q'; with nothing on screen to get
+    // through the quit didn't take, eaten by a prompt or tried from
+    // where there are no stairs, and it's sent again
+    if state.quitting {
+        return if prompt.contains("[ynq]") {
+            Decision::new("skip-disclosure", "q")
+        } else if more {
+            Decision::new("dismiss-more", "\r")
+        } else {
+            Decision::new("quit-again", quit_string(false))
+        };
+    }
+
+    if state.checking_inventory {
+        if let Some(menu) = menu {
+            game.read_inventory(&menu);
+            if menu.page < menu.pages {
+                return Decision::new("next-inventory-page", ">");
+            }
+        }
+        // either the last page is read or there was no menu at all
+        // because the character isn't carrying anything
+        state.checking_inventory = false;
+        let carried: Vec<_> = game.get_inventory().iter()
+            .filter(|item| rules.wants(item))
+            .map(|item| (item.inventory_letter, item.quantity))
+            .collect();
+        // only something that wasn't there before the pickup counts,
+        // a Wizard's starting wand is no find
+        let picked_up = state.carried.as_ref().map(|before| carried.iter().any(|entry| !before.contains(entry)));
+        state.carried = Some(carried);
+        // a pickup that didn't land would only be tried again and again
+        if picked_up == Some(false) {
+            state.quitting = true;
+            return Decision::new("quit-pickup-failed", &format!("\x1b{}", quit_string(state.stairs)));
+        }
+        state.have_picked = picked_up == Some(true);
+        return Decision::new("close-inventory", "\x1b");
+    }
+    if let Some(menu) = menu {
+        if let Some(key) = character.menu_key(&menu) {
+            return Decision::new("pick-character", &key.to_string());
+        }
+        return Decision::new("select-menu", &menu.select_keys(|entry| entry.item.as_ref().is_some_and(|item| rules.wants(item))));
+    }
+    if state.pickup_pending {
+        state.pickup_pending = false;
+        state.checking_inventory = true;
+        game.clear_inventory();
+        return Decision::new("check-pickup", "i");
+    }
+    // what the character starts with is read before anything is picked
+    // up, once the intro is out of the way
+    if state.carried.is_none() && !more && game.get_map().player().is_some() {
+        state.checking_inventory = true;
+        game.clear_inventory();
+        return Decision::new("read-inventory", "i");
+    }
+
+    if game.get_dlvl() != state.dlvl {
+        state.inspected.clear();
+        state.dlvl = game.get_dlvl();
+    }
+
+    // a travel that stopped short was interrupted, by a monster coming
+    // into view or something in the way, and the move is planned again
+    if let Some(dest) = state.travelling.take() {
+        if game.get_map().player() == Some(dest) {
+            state.have_looked = false;
+        }
+    }
+
+    let look = parse_pile_window(&rows)
+        .or_else(|| parse_look_message(&read_buf[read_buf.len() - 512 ..]));
+    let seen = look.as_ref().map(look_summary);
+    if let Some(feature) = look {
         match feature {
-            LookFeet::Loot(item) => {
-                match item {
-                    Item::Wand(_) => { *have_picked = true; return Some(","); },
-                    Item::Strange(_) => ()
+            LookFeet::Loot(item, upstairs) => {
+                if upstairs {
+                    game.note_terrain(Terrain::UpStairs);
                 }
+                if rules.wants(&item) {
+                    state.pickup_pending = true;
+                    return Decision::new("pick-up", ",").with_look(&seen);
+                }
+            },
+            LookFeet::Pile(items, _) => {
+                state.have_looked = true;
+                // the window has to go before anything else can happen
+                if items.iter().any(|item| rules.wants(item)) {
+                    state.pickup_pending = true;
+                    return Decision::new("pick-up-pile", "\x1b,").with_look(&seen);
+                }
+                return Decision::new("close-pile", "\x1b").with_look(&seen);
             },
-            LookFeet::UpStairs => *stairs = true,
-            LookFeet::DownStairs => *stairs = false,
-            LookFeet::Nothing => *stairs = false
+            LookFeet::UpStairs => game.note_terrain(Terrain::UpStairs),
+            LookFeet::DownStairs => game.note_terrain(Terrain::DownStairs),
+            LookFeet::Nothing => ()
         }
-        *have_looked = true;
+        state.have_looked = true;
+    }
+    // worked out afresh every frame, the quit only goes up the stairs
+    // while the player is still standing on them
+    if let Some((y, x)) = game.get_map().player() {
+        state.stairs = game.get_map().terrain(y, x) == Terrain::UpStairs;
+    }
+    // the intro text and the odd message that overflows the top line
+    // hide the player until they're dismissed
+    if more && game.get_map().player().is_none() {
+        return Decision::new("dismiss-more", "\r").with_look(&seen);
+    }
+    if !state.have_looked {
+        return Decision::new("look", ":").with_look(&seen);
     }
-    if *have_looked {
-        if let Some((dy, dx)) = get_wand_vector(term) {
-            if dy < north && dy > south && dx < west && dx > east {
-                *have_looked = false;
-                Some(get_direction_key(dx, dy))
+    // the look is done and nothing wanted was picked up, so whatever
+    // lies here isn't worth coming back to
+    if let Some(square) = game.get_map().player() {
+        state.inspected.insert(square);
+    }
+    let decision = match walk_to_target(game.get_map(), &rules.classes(), &state.inspected) {
+        Some(Move::Step(key, arriving)) => {
+            // look again once standing on the target
+            if arriving {
+                state.have_looked = false;
+            }
+            Decision::new("step", &key.to_string())
+        },
+        Some(Move::Travel(keys, dest, arriving)) => {
+            state.travelling = arriving.then_some(dest);
+            Decision::new("travel", &keys)
+        },
+        // a locked door gets kicked until it gives
+        Some(Move::Door(key, door)) if state.door.is_none_or(|(at, tries)| at != door || tries < DOOR_TRIES) => {
+            let tries = match state.door {
+                Some((at, tries)) if at == door => tries + 1,
+                _ => 1
+            };
+            state.door = Some((door, tries));
+            if tries == 1 {
+                Decision::new("open-door", &format!("o{}", key))
             } else {
-                Some(quit_string(*stairs))
+                Decision::new("kick-door", &format!("\x04{}", key))
             }
-        } else {
-            Some(quit_string(*stairs))
+        },
+        Some(Move::Door(..)) => {
+            state.quitting = true;
+            Decision::new("quit-door-stuck", quit_string(state.stairs))
+        },
+        None => {
+            state.quitting = true;
+            Decision::new("quit-no-target", quit_string(state.stairs))
+        }
+    };
+    decision.with_look(&seen)
+}
+
+// how long the pty has to stay silent before a frame counts as drawn,
+// and how long to put up with a hidden cursor before acting anyway
+const FRAME_QUIET: Duration = Duration::from_millis(50);
+const FRAME_STALL: Duration = Duration::from_millis(1000);
+// how long the bot waits on a screen that doesn't change before trying
+// to shake the game loose
+const HANG_TIMEOUT: Duration = Duration::from_secs(10);
+
+// who's at the keyboard, the hotkey swaps between the first two, and
+// once a target turns up the game is the user's for good
+#[derive(Clone, Copy, PartialEq)]
+enum Control {
+    Bot,
+    Manual,
+    Found
+}
+
+impl Control {
+    fn label(self) -> &'static str {
+        match self {
+            Control::Bot => "titm: bot",
+            Control::Manual => "titm: manual",
+            Control::Found => "titm: found one, your game"
+        }
+    }
+
+    fn name(self) -> &'static str {
+        match self {
+            Control::Bot => "bot",
+            Control::Manual => "manual",
+            Control::Found => "found"
         }
-    } else {
-        Some(":")
     }
 }
 
-const SHOW_CURSOR_SEQUENCE: &'static str = "\x1b[?25h";
+// the record of a bot frame in the log, the same key=value fields
+// every time with '-' for anything not known; the bot's state is as
+// the decision left it
+fn log_frame(frame: u32, game: &NetHackData, state: &BotState, decision: &Decision) {
+    let square = |pos: Option<(usize, usize)>| pos.map_or("-".to_string(), |(y, x)| format!("{},{}", y, x));
+    let quoted = |text: Option<&str>| text.map_or("-".to_string(), |text| format!("{:?}", text));
+    let number = |n: Option<u32>| n.map_or("-".to_string(), |n| n.to_string());
+    debug!("frame={} player={} character={} dlvl={} hp={} turn={} looked={} stairs={} pickup_pending={} \
+            checking_inventory={} picked={} travelling={} quitting={} look={} action={} keys={}",
+           frame, square(game.get_map().player()),
+           game.get_character().map_or("-".to_string(), |(rank, align)| character::label(rank, align)),
+           number(game.get_dlvl()), game.get_hp().map_or("-".to_string(), |(hp, maxhp)| format!("{}/{}", hp, maxhp)),
+           number(game.get_turns()),
+           state.have_looked, state.stairs, state.pickup_pending, state.checking_inventory, state.have_picked,
+           square(state.travelling), state.quitting,
+           quoted(decision.look.as_deref()), decision.action, quoted(Some(&decision.keys)));
+}
+
+fn send_keys(pty_writer: &mut PtyWriter, keys: &str) -> Result<()> {
+    pty_writer.write_all(keys.as_bytes())?;
+    pty_writer.flush()?;
+    Ok(())
+}
 
-fn main() -> Result<()> {
-    match fork_terminal()? {
-        TermFork::Parent(pty_reader, mut pty_writer, mut terminal) => {
-            let mut stdout = stdout().into_raw_mode().unwrap();
-            let mut processor = Processor::new();
-            let mut read_buf= [0u8; 4096];
-            let mut have_looked = false;
-            let mut have_picked = true;
-            let mut stairs = false;
-
-            for c in pty_reader {
-                processor.advance(&mut terminal, c, &mut stdout);
+// where the log goes when -v is given without --log
+const DEFAULT_LOG: &str = "titm.log";
+
+// ^], which NetHack doesn't use for anything
+const TOGGLE_KEY: u8 = 0x1d;
+
+// headless there's nobody to show the game to or take keys from, so a
+// find ends the game there and then, which has NetHack save it
+fn run_game<W: Write>(rules: &TargetRules, character: &Character, headless: bool, stdout: &mut W,
+                      mut pty_reader: PtyReader, mut pty_writer: PtyWriter, mut screen: GameScreen) -> Result<(Outcome, Option<String>)> {
+    let mut game = NetHackData::new();
+    let mut frames = FrameDetector::new(FRAME_QUIET, FRAME_STALL);
+    if !headless {
+        frames = frames.with_input(stdin().as_raw_fd());
+    }
+    let mut read_buf= [0u8; 4096];
+    let mut state = BotState::new();
+    let mut control = Control::Bot;
+    // set if the game that started isn't the character asked for, which
+    // only the first statusline can say: the rank title changes as the
+    // character levels up
+    let mut checked = false;
+    let mut mismatch = None;
+    // bot frames so far, to tell them apart in the log
+    let mut frame = 0;
+
+    loop {
+        match frames.next_event(&mut pty_reader, &screen)? {
+            Event::Byte(c) => {
+                screen.advance(c, stdout);
+                shift(&mut read_buf);
                 read_buf[read_buf.len() - 1] = c;
-                stdout.write(&read_buf[read_buf.len() - 1..])?;
-                stdout.flush()?;
-                if str::from_utf8(&read_buf[read_buf.len() - 6 ..]).unwrap() == SHOW_CURSOR_SEQUENCE {
-                    if let Some(out) = respond(&terminal, &read_buf, &mut have_picked, &mut have_looked, &mut stairs) {
-                        pty_writer.write(out.as_bytes())?;
+                if !headless {
+                    stdout.write_all(&read_buf[read_buf.len() - 1..])?;
+                    stdout.flush()?;
+                }
+            },
+            Event::Input(keys) => {
+                for key in keys {
+                    if key == TOGGLE_KEY {
+                        // there's no handing a find back to the bot
+                        control = match control {
+                            Control::Bot => Control::Manual,
+                            // whatever the user did, the bot starts
+                            // over from a fresh look around, without
+                            // waiting for the game to draw something
+                            Control::Manual => {
+                                state = BotState::new();
+                                frames.refresh();
+                                Control::Bot
+                            },
+                            Control::Found => Control::Found
+                        };
+                        info!("event=control control={}", control.name());
+                        overlay::draw(stdout, control.label())?;
+                    } else if control != Control::Bot {
+                        pty_writer.write_all(&[key])?;
                     }
                 }
-                pty_writer.flush()?;
-                shift(&mut read_buf);
+            },
+            Event::Exited(status) => {
+                info!("event=exited status={:?}", format!("{:?}", status));
+                break;
+            },
+            Event::Eof => break,
+            Event::Timer => {
+                if control == Control::Bot {
+                    warn!("event=hang seconds={} keys={:?}", HANG_TIMEOUT.as_secs(), "\x1b");
+                    send_keys(&mut pty_writer, "\x1b")?;
+                    frames.refresh();
+                }
+            },
+            Event::Resize => {
+                let win_size = get_winsize()?;
+                pty_writer.resize(&win_size)?;
+                screen.resize(sizeinfo_from(win_size));
+            },
+            Event::ScreenStable => {
+                game.update(&screen)?;
+                if !checked {
+                    if let Some((rank, align)) = game.get_character() {
+                        checked = true;
+                        mismatch = character.check(rank, align).err();
+                        if let Some(ref e) = mismatch {
+                            warn!("event=wrong-character error={:?}", e.to_string());
+                        }
+                    }
+                }
+                if state.have_picked && headless {
+                    control = Control::Found;
+                    break;
+                }
+                if state.have_picked && control == Control::Bot {
+                    // stop dead and get the user's attention
+                    info!("event=found");
+                    control = Control::Found;
+                    stdout.write_all(b"\x07")?;
+                }
+                if !headless {
+                    overlay::draw(stdout, control.label())?;
+                }
+                if control != Control::Bot {
+                    frames.cancel_timer();
+                    continue;
+                }
+                frames.set_timer(HANG_TIMEOUT);
+                frame += 1;
+                if mismatch.is_some() && !state.quitting {
+                    state.quitting = true;
+                    let decision = Decision::new("quit-wrong-character", quit_string(false));
+                    log_frame(frame, &game, &state, &decision);
+                    send_keys(&mut pty_writer, quit_string(false))?;
+                    continue;
+                }
+                let decision = respond(&screen, &mut game, rules, character, &read_buf, &mut state);
+                log_frame(frame, &game, &state, &decision);
+                send_keys(&mut pty_writer, &decision.keys)?;
+            },
+        }
+    }
+    let status = pty_reader.close()?;
+    if let Some(e) = mismatch {
+        return Err(e);
+    }
+    let outcome = match (control, status) {
+        (Control::Found, _) => Outcome::Found,
+        (_, WaitStatus::Exited(_, 0)) if state.quitting => Outcome::Rerolled,
+        (_, WaitStatus::Exited(_, 0)) => Outcome::Ended,
+        _ => Outcome::Crashed
+    };
+    Ok((outcome, game.get_character().map(|(rank, align)| character::label(rank, align))))
+}
+
+// targets, the character to play and how to start the game all come
+// out of the same file
+fn load_config(cli: &Cli) -> Result<(TargetRules, Character, GameConfig)> {
+    let (rules, character, mut game) = match cli.config {
+        Some(ref path) => {
+            let text = fs::read_to_string(path)
+                .map_err(|e| format!("could not read {}: {}", path.display(), e))?;
+            (TargetRules::parse(&text)?, Character::parse(&text)?, GameConfig::parse(&text)?)
+        },
+        None => (TargetRules::default(), Character::default(), GameConfig::default())
+    };
+    if let Some(ref command) = cli.game {
+        game.set_command(command);
+    }
+    Ok((rules, character, game))
+}
+
+// never returns, the child is done once the game exits
+fn exec_game(game: &GameConfig, character: &Character) -> ! {
+    // straight out of the child so none of the parent's state gets
+    // cleaned up twice
+    let status = match game.command(&character.args()).status() {
+        Ok(status) => status,
+        Err(e) => {
+            eprintln!("could not run {}: {}", game.name(), e);
+            process::exit(127);
+        }
+    };
+    process::exit(status.code().unwrap_or(1));
+}
+
+// each game is recorded under a temporary name, then renamed once it's
+// known who played it and how it went
+fn run(cli: &Cli, max_runs: Option<u32>, headless: Option<(u16, u16)>, record: Option<&Path>) -> Result<()> {
+    let (rules, character, game) = load_config(cli)?;
+    game.prepare()?;
+    signals::watch(Signal::SIGCHLD)?;
+    let mut stdout: Box<dyn Write> = match headless {
+        Some(_) => Box::new(io::sink()),
+        None => {
+            signals::watch(Signal::SIGWINCH)?;
+            Box::new(stdout().into_raw_mode()?)
+        }
+    };
+    let mut runs = 0;
+    loop {
+        let win_size = match headless {
+            Some((cols, rows)) => virtual_winsize(rows, cols),
+            None => get_winsize()?
+        };
+        match fork_terminal(win_size)? {
+            TermFork::Parent(mut pty_reader, pty_writer, screen) => {
+                runs += 1;
+                info!("event=start game={}", runs);
+                let started = SystemTime::now();
+                let stamp = started.duration_since(UNIX_EPOCH).map_or(0, |d| d.as_secs());
+                let recording = match record {
+                    Some(dir) => {
+                        fs::create_dir_all(dir)?;
+                        let path = dir.join(format!("{}-playing.ttyrec", stamp));
+                        pty_reader.record(File::create(&path)?);
+                        Some(path)
+                    },
+                    None => None
+                };
+                // a game that ends in an error still gets its recording
+                // named and its run recorded before the error goes up
+                let (outcome, label, failure) = match run_game(&rules, &character, headless.is_some(), &mut stdout, pty_reader, pty_writer, screen) {
+                    Ok((outcome, label)) => (outcome, label, None),
+                    Err(e) => (Outcome::Crashed, None, Some(e))
+                };
+                if let Some(path) = recording {
+                    let label = label.unwrap_or_else(|| "unknown".to_string());
+                    fs::rename(&path, path.with_file_name(format!("{}-{}-{}.ttyrec", stamp, label, outcome.name())))?;
+                }
+                // so the next game doesn't take this one's exit as its own
+                signals::take(Signal::SIGCHLD);
+                info!("event=end game={} outcome={}", runs, outcome.name());
+                runs::record(&cli.runs, &RunRecord::new(started, outcome))?;
+                if let Some(e) = failure {
+                    return Err(e);
+                }
+                let message = match outcome {
+                    Outcome::Rerolled if max_runs.is_none_or(|max| runs < max) => continue,
+                    Outcome::Rerolled => format!("nothing found in {} games", runs),
+                    Outcome::Found => format!("found a target on run {}", runs),
+                    Outcome::Ended => format!("{} exited on its own on run {}", game.name(), runs),
+                    Outcome::Crashed => format!("{} crashed on run {}", game.name(), runs)
+                };
+                drop(stdout);
+                println!("{}", message);
+                return Ok(());
+            },
+            TermFork::Child => exec_game(&game, &character)
+        }
+    }
+}
+
+// the game with the keyboard plugged straight into it
+fn watch(cli: &Cli) -> Result<()> {
+    let (_, character, game) = load_config(cli)?;
+    game.prepare()?;
+    signals::watch(Signal::SIGWINCH)?;
+    match fork_terminal(get_winsize()?)? {
+        TermFork::Parent(mut pty_reader, mut pty_writer, _) => {
+            let mut stdout = stdout().into_raw_mode()?;
+            let mut stdin = stdin();
+            let mut buf = [0u8; 4096];
+            loop {
+                let mut fds = [PollFd::new(pty_reader.as_raw_fd(), PollFlags::POLLIN),
+                               PollFd::new(stdin.as_raw_fd(), PollFlags::POLLIN)];
+                match poll(&mut fds, -1) {
+                    Ok(_) => (),
+                    Err(nix::Error::Sys(Errno::EINTR)) => {
+                        if signals::take(Signal::SIGWINCH) {
+                            pty_writer.resize(&get_winsize()?)?;
+                        }
+                        continue;
+                    },
+                    Err(e) => return Err(e.into())
+                }
+                if fds[0].revents().is_some_and(|r| !r.is_empty()) {
+                    // EIO from the pty is how the child going away shows
+                    match pty_reader.read(&mut buf) {
+                        Ok(0) | Err(_) => break,
+                        Ok(n) => {
+                            stdout.write_all(&buf[..n])?;
+                            stdout.flush()?;
+                        }
+                    }
+                }
+                if fds[1].revents().is_some_and(|r| !r.is_empty()) {
+                    let n = stdin.read(&mut buf)?;
+                    if n == 0 {
+                        break;
+                    }
+                    pty_writer.write_all(&buf[..n])?;
+                }
             }
+            pty_reader.close()?;
             Ok(())
         },
-        TermFork::Child => {
-            Command::new("nethack").status().expect("could not execute local nethack");
-            Ok(())
+        TermFork::Child => exec_game(&game, &character)
+    }
+}
+
+// waits between frames are cut short at a second so idle stretches
+// don't hold the replay up
+fn replay(path: &Path, speed: f64) -> Result<()> {
+    let mut reader = TtyrecReader::new(BufReader::new(File::open(path)?));
+    let mut stdout = stdout();
+    let mut last = None;
+    while let Some(frame) = reader.read_frame()? {
+        if let Some(last) = last {
+            let wait = frame.time.checked_sub(last).unwrap_or_default().div_f64(speed.max(0.01));
+            thread::sleep(wait.min(Duration::from_secs(1)));
         }
+        last = Some(frame.time);
+        stdout.write_all(&frame.data)?;
+        stdout.flush()?;
     }
+    Ok(())
 }
 
+// runs the bot over a recorded game: a frame counts as drawn where the
+// recording went quiet for as long as a live game would have to, and
+// the bot's answer is noted but of course never reaches the game
+fn replay_decisions<R: Read>(reader: &mut TtyrecReader<R>, (cols, rows): (u16, u16), rules: &TargetRules,
+                             character: &Character) -> Result<Vec<(Duration, String)>> {
+    let mut screen = GameScreen::new(sizeinfo_from(virtual_winsize(rows, cols)));
+    let mut game = NetHackData::new();
+    let mut read_buf = [0u8; 4096];
+    let mut state = BotState::new();
+    let mut decisions = Vec::new();
+    let mut start = None;
+    let mut last: Option<Duration> = None;
+
+    loop {
+        let frame = reader.read_frame()?;
+        if let Some(time) = last {
+            let gap = frame.as_ref().map(|frame| frame.time.checked_sub(time).unwrap_or_default());
+            // the end of the recording is as quiet as it gets
+            let quiet = gap.is_none_or(|gap| gap >= FRAME_STALL || (gap >= FRAME_QUIET && screen.cursor_visible()));
+            if quiet {
+                game.update(&screen)?;
+                let out = respond(&screen, &mut game, rules, character, &read_buf, &mut state);
+                decisions.push((time.saturating_sub(start.unwrap_or(time)), out.keys));
+            }
+        }
+        let frame = match frame {
+            Some(frame) => frame,
+            None => break
+        };
+        for c in frame.data.iter() {
+            screen.advance(*c, &mut io::sink());
+            shift(&mut read_buf);
+            read_buf[read_buf.len() - 1] = *c;
+        }
+        start = start.or(Some(frame.time));
+        last = Some(frame.time);
+    }
+    Ok(decisions)
+}
+
+// feeds captured terminal output through the emulator and describes
+// what each of the parsers, and the bot's pathing, makes of the final
+// screen; the snapshot tests compare this against golden files
+fn screen_report(bytes: &[u8], (cols, rows): (u16, u16), rules: &TargetRules) -> Result<String> {
+    let mut screen = GameScreen::new(sizeinfo_from(virtual_winsize(rows, cols)));
+    for c in bytes.iter() {
+        screen.advance(*c, &mut io::sink());
+    }
+    let mut game = NetHackData::new();
+    game.update(&screen)?;
+    let text = screen.get_rows();
+
+    let mut report = vec!["screen:".to_string()];
+    for row in text.iter() {
+        report.push(format!("  {}", row.iter().collect::<String>()).trim_end().to_string());
+    }
+    for window in screen.get_subwindows()? {
+        report.push(format!("window: {:?} at {:?}", window.get_size(), window.get_origin()));
+    }
+    if let Some(menu) = read_menu(&text) {
+        report.push(format!("menu: page {} of {}", menu.page, menu.pages));
+        for entry in menu.entries.iter() {
+            let item = entry.item.as_ref().map_or(String::new(), |item| format!(" => {}", item));
+            report.push(format!("  {} {} {}{}", entry.letter, if entry.selected { '+' } else { '-' }, entry.text, item));
+        }
+    }
+    match parse_pile_window(&text).or_else(|| parse_look_message(bytes)) {
+        Some(LookFeet::Loot(item, upstairs)) =>
+            report.push(format!("look: {}{}", item, if upstairs { " on the up stairs" } else { "" })),
+        Some(LookFeet::Pile(items, upstairs)) => {
+            report.push(format!("look: pile{}", if upstairs { " on the up stairs" } else { "" }));
+            for item in items.iter() {
+                report.push(format!("  {}", item));
+            }
+        },
+        look => report.push(format!("look: {:?}", look))
+    }
+    report.push(format!("player: {:?}", game.get_map().player()));
+    report.push(format!("character: {:?}", game.get_character()));
+    report.push(format!("move: {:?}", walk_to_target(game.get_map(), &rules.classes(), &HashSet::new())));
+    Ok(report.join("\n") + "\n")
+}
+
+fn main() -> Result<()> {
+    let cli = Cli::parse();
+    // the log would only make a mess of the game on screen, so asking
+    // for more of it without saying where still puts it in a file
+    let log = cli.log.clone().or_else(|| (cli.verbose > 0).then(|| PathBuf::from(DEFAULT_LOG)));
+    if let Some(ref path) = log {
+        let file = OpenOptions::new().create(true).append(true).open(path)?;
+        dup2(file.as_raw_fd(), io::stderr().as_raw_fd())?;
+        logger::init(match cli.verbose {
+            0 => LevelFilter::Info,
+            1 => LevelFilter::Debug,
+            _ => LevelFilter::Trace
+        })?;
+    }
+
+    match cli.mode {
+        Mode::Run { max_runs, headless, size, ref record, no_record } =>
+            run(&cli, max_runs, headless.then_some(size), (!no_record).then_some(record.as_path())),
+        Mode::Watch => watch(&cli),
+        Mode::Replay { ref ttyrec, bot: true, size, .. } => {
+            let (rules, character, _) = load_config(&cli)?;
+            let mut reader = TtyrecReader::new(BufReader::new(File::open(ttyrec)?));
+            for (time, keys) in replay_decisions(&mut reader, size, &rules, &character)? {
+                println!("{:>9.3}s  {:?}", time.as_secs_f64(), keys);
+            }
+            Ok(())
+        },
+        Mode::Replay { ref ttyrec, speed, .. } => replay(ttyrec, speed),
+        Mode::Parse { ref file, size } => {
+            let (rules, _, _) = load_config(&cli)?;
+            print!("{}", screen_report(&fs::read(file)?, size, &rules)?);
+            Ok(())
+        },
+        Mode::Stats => {
+            print!("{}", runs::summary(&runs::load(&cli.runs)?));
+            Ok(())
+        }
+    }
+}
@@ -0,0 +1,160 @@
+use terminal_emulator::ansi::{Color, NamedColor};
+use terminal_emulator::term::cell::{Cell, Flags};
+use crate::nethack::ItemClass;
+use crate::screen::SubWindow;
+
+pub const MAP_ROWS: usize = 21;
+pub const MAP_COLS: usize = 80;
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Terrain {
+    Unknown,
+    Wall,
+    Floor,
+    OpenDoor,
+    ClosedDoor,
+    Corridor,
+    UpStairs,
+    DownStairs,
+    Fountain,
+    Altar,
+    Water,
+    Trap
+}
+
+impl Terrain {
+    // whether the player can step onto it, closed doors have to be
+    // opened first and traps are best left alone
+    pub fn is_walkable(self) -> bool {
+        matches!(self, Terrain::Floor | Terrain::OpenDoor | Terrain::Corridor
+                 | Terrain::UpStairs | Terrain::DownStairs | Terrain::Fountain | Terrain::Altar)
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum MapCell {
+    Blank,
+    Terrain(Terrain),
+    Item(ItemClass),
+    Boulder,
+    Monster(char),
+    Player
+}
+
+// NetHack draws doors in brown, which is how an open door tells itself
+// apart from a wall and a closed one from a spellbook
+fn is_brown(cell: &Cell) -> bool {
+    cell.fg == Color::Named(NamedColor::Yellow) && !cell.flags.contains(Flags::BOLD)
+}
+
+fn classify(cell: &Cell) -> MapCell {
+    let brown = is_brown(cell);
+    match cell.c {
+        ' ' => MapCell::Blank,
+        '|' | '-' if brown => MapCell::Terrain(Terrain::OpenDoor),
+        '+' if brown => MapCell::Terrain(Terrain::ClosedDoor),
+        '|' | '-' | '─' | '│' | '┌' | '┐' | '└' | '┘' | '├' | '┤' | '┬' | '┴' | '┼' => MapCell::Terrain(Terrain::Wall),
+        '.' | '·' => MapCell::Terrain(Terrain::Floor),
+        '#' => MapCell::Terrain(Terrain::Corridor),
+        '<' => MapCell::Terrain(Terrain::UpStairs),
+        '>' => MapCell::Terrain(Terrain::DownStairs),
+        '{' => MapCell::Terrain(Terrain::Fountain),
+        '_' => MapCell::Terrain(Terrain::Altar),
+        '}' => MapCell::Terrain(Terrain::Water),
+        '^' => MapCell::Terrain(Terrain::Trap),
+        '`' | '0' => MapCell::Boulder,
+        c => match ItemClass::all().iter().find(|class| class.glyph() == c) {
+            Some(class) => MapCell::Item(*class),
+            None if c.is_ascii_alphabetic() || "@&;:'~".contains(c) => MapCell::Monster(c),
+            None => MapCell::Blank
+        }
+    }
+}
+
+// what's on each square of the level, kept between frames so that the
+// terrain under an object or monster isn't forgotten when it's covered
+pub struct NHMap {
+    glyphs: Vec<Vec<Cell>>,
+    cells: Vec<Vec<MapCell>>,
+    terrain: Vec<Vec<Terrain>>,
+    player: Option<(usize, usize)>
+}
+
+impl NHMap {
+    pub fn new() -> Self {
+        NHMap {
+            glyphs: vec![vec![Cell::default(); MAP_COLS]; MAP_ROWS],
+            cells: vec![vec![MapCell::Blank; MAP_COLS]; MAP_ROWS],
+            terrain: vec![vec![Terrain::Unknown; MAP_COLS]; MAP_ROWS],
+            player: None
+        }
+    }
+
+    // the curses interface puts the map in a window of its own, the tty
+    // one draws it from the line below the messages
+    pub fn find_origin(windows: &[SubWindow]) -> (usize, usize) {
+        windows.iter()
+            .find(|window| window.get_size().0 == MAP_ROWS)
+            .map_or((1, 0), |window| window.get_origin())
+    }
+
+    // only squares whose glyph changed since the last frame are looked
+    // at again
+    pub fn update(&mut self, screen: &[Vec<Cell>], origin: (usize, usize), cursor: (usize, usize)) {
+        let (top, left) = origin;
+        for y in 0 .. MAP_ROWS {
+            for x in 0 .. MAP_COLS {
+                let cell = match screen.get(top + y).and_then(|row| row.get(left + x)) {
+                    Some(cell) => *cell,
+                    None => Cell::default()
+                };
+                if cell.c == self.glyphs[y][x].c && cell.fg == self.glyphs[y][x].fg {
+                    continue;
+                }
+                self.glyphs[y][x] = cell;
+                self.cells[y][x] = classify(&cell);
+                match self.cells[y][x] {
+                    MapCell::Terrain(terrain) => self.terrain[y][x] = terrain,
+                    // objects lie on the floor unless we knew better
+                    MapCell::Item(_) | MapCell::Boulder if self.terrain[y][x] == Terrain::Unknown =>
+                        self.terrain[y][x] = Terrain::Floor,
+                    _ => ()
+                }
+            }
+        }
+
+        self.player = None;
+        if cursor.0 >= top && cursor.1 >= left {
+            let (y, x) = (cursor.0 - top, cursor.1 - left);
+            if y < MAP_ROWS && x < MAP_COLS && self.glyphs[y][x].c == '@' {
+                self.cells[y][x] = MapCell::Player;
+                self.player = Some((y, x));
+            }
+        }
+    }
+
+    // the map isn't read while something covers it, and the cursor is
+    // off in whatever that is rather than on the player
+    pub fn hide_player(&mut self) {
+        if let Some((y, x)) = self.player.take() {
+            self.cells[y][x] = classify(&self.glyphs[y][x]);
+        }
+    }
+
+    pub fn cell(&self, y: usize, x: usize) -> MapCell {
+        self.cells[y][x]
+    }
+
+    pub fn terrain(&self, y: usize, x: usize) -> Terrain {
+        self.terrain[y][x]
+    }
+
+    // for squares the map can't see into, like the one under the '@'
+    pub fn set_terrain(&mut self, (y, x): (usize, usize), terrain: Terrain) {
+        self.terrain[y][x] = terrain;
+    }
+
+    pub fn player(&self) -> Option<(usize, usize)> {
+        self.player
+    }
+}
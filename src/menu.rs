@@ -0,0 +1,92 @@
+use regex::Regex;
+use crate::nethack::{ItemClass, NHInvItem};
+use crate::objects::parse_object;
+use crate::screen::row_text;
+
+pub struct MenuEntry {
+    pub letter: char,
+    pub class: Option<ItemClass>, // from the heading the entry is listed under
+    pub selected: bool,
+    pub text: String,
+    pub item: Option<NHInvItem>
+}
+
+// one page of a NetHack menu as it's drawn on screen, e.g.
+//
+//     Pick up what?
+//
+//     Wands
+//     a - an oak wand
+//     Gems/Stones
+//     b + 2 gray stones
+//     (1 of 2)
+//
+// entries are drawn with '+' instead of '-' once they're selected
+pub struct Menu {
+    pub entries: Vec<MenuEntry>,
+    pub page: usize,
+    pub pages: usize
+}
+
+// finds the page indicator at the bottom of the menu, which also marks
+// the column the menu is drawn from
+fn find_indicator(rows: &[Vec<char>]) -> Option<(usize, usize, usize, usize)> {
+    let re = Regex::new(r"\((?:end|(\d+) of (\d+))\)\s*│?\s*$").unwrap();
+    for (line, row) in rows.iter().enumerate().rev() {
+        let text: String = row.iter().collect();
+        if let Some(caps) = re.captures(&text) {
+            let start = caps.get(0).unwrap().start();
+            let col = text[..start].chars().count();
+            let (page, pages) = match (caps.get(1), caps.get(2)) {
+                (Some(p), Some(n)) => (p.as_str().parse().ok()?, n.as_str().parse().ok()?),
+                _ => (1, 1)
+            };
+            return Some((line, col, page, pages));
+        }
+    }
+    None
+}
+
+pub fn read_menu(rows: &[Vec<char>]) -> Option<Menu> {
+    let (line, col, page, pages) = find_indicator(rows)?;
+    let entry_re = Regex::new(r"^([a-zA-Z$#*]) ([-+#]) (.+)$").unwrap();
+
+    let mut entries = Vec::new();
+    let mut class = None;
+    for row in rows[..line].iter() {
+        let text = row_text(row, col);
+        if let Some(caps) = entry_re.captures(&text) {
+            entries.push(MenuEntry {
+                letter: caps[1].chars().next()?,
+                class,
+                selected: &caps[2] != "-",
+                text: caps[3].to_string(),
+                item: parse_object(&caps[3])
+            });
+        } else if let Some(heading) = ItemClass::from_name(text.trim()) {
+            class = Some(heading);
+        }
+    }
+
+    if entries.is_empty() {
+        return None;
+    }
+    Some(Menu { entries, page, pages })
+}
+
+impl Menu {
+    // keys that select every wanted entry on this page that isn't
+    // already selected, then either turn the page or confirm
+    pub fn select_keys<F: Fn(&MenuEntry) -> bool>(&self, wanted: F) -> String {
+        let mut keys: String = self.entries.iter()
+            .filter(|e| !e.selected && wanted(e))
+            .map(|e| e.letter)
+            .collect();
+        if self.page < self.pages {
+            keys.push('>');
+        } else {
+            keys.push('\r');
+        }
+        keys
+    }
+}
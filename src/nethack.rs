@@ -1,5 +1,17 @@
+// the game as read off the screen: the statusline, the level map and
+// the inventory
+use std::error;
+use log::trace;
+use crate::map::{NHMap, Terrain};
+use crate::menu::{read_menu, Menu};
+use crate::screen::{GameScreen, SubWindow};
 
+type Result<T> = std::result::Result<T, Box<dyn error::Error>>;
 
+
+// the numbers here and in Strength are only read through Debug, for
+// the status record in the log
+#[allow(dead_code)]
 #[derive(Debug)]
 enum CharLevel {
     XLvl(u32),
@@ -7,6 +19,7 @@ enum CharLevel {
     HD(u32)
 }
 
+#[allow(dead_code)]
 #[derive(Debug)]
 enum Strength {
     Normal(u32),
@@ -26,17 +39,31 @@ struct AbilityScores {
 #[derive(Debug)]
 enum Class {
     Rank(String),
+    // likewise the monster's name
+    #[allow(dead_code)]
     Polyform(String)
 }
 
-#[derive(Debug)]
-enum Align {
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Align {
     Lawful,
     Neutral,
     Chaotic,
     Unaligned
 }
 
+impl Align {
+    pub fn from_name(name: &str) -> Option<Self> {
+        match name.to_lowercase().as_str() {
+            "lawful" | "law" => Some(Align::Lawful),
+            "neutral" | "neu" => Some(Align::Neutral),
+            "chaotic" | "cha" => Some(Align::Chaotic),
+            "unaligned" => Some(Align::Unaligned),
+            _ => None
+        }
+    }
+}
+
 #[derive(Debug)]
 struct NHStats {
     dlvl: u32,
@@ -99,7 +126,7 @@ impl NHStats {
                             self.gold = n;
                         },
                         "HP" => {
-                            let split_maxhp: Vec<&str> = value.split(|c| c == '(' || c == ')').collect();
+                            let split_maxhp: Vec<&str> = value.split(['(', ')']).collect();
                             if split_maxhp.len() >= 2 {
                                 if let Ok(n) = split_maxhp[0].parse::<u32>() {
                                     self.hp = n;
@@ -110,7 +137,7 @@ impl NHStats {
                             }
                         },
                         "Pw" => {
-                            let split_maxpw: Vec<&str> = value.split(|c| c == '(' || c == ')').collect();
+                            let split_maxpw: Vec<&str> = value.split(['(', ')']).collect();
                             if split_maxpw.len() >= 2 {
                                 if let Ok(n) = split_maxpw[0].parse::<u32>() {
                                     self.pw = n;
@@ -197,7 +224,7 @@ impl NHStats {
         }
 
         for i in 0 .. saved_tokens.len() {
-            if i >= 1 && saved_tokens[i].as_str() == "the" && i < saved_tokens.len() {
+            if i >= 1 && saved_tokens[i].as_str() == "the" && i + 1 < saved_tokens.len() {
                 self.name = saved_tokens[i-1].clone();
                 match self.level {
                     CharLevel::HD(_) => self.rank = Class::Polyform(saved_tokens[i+1].clone()),
@@ -212,12 +239,15 @@ impl NHStats {
 
 type NHInv = Vec<NHInvItem>;
 
-enum ItemClass {
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum ItemClass {
+    Coins,
     Weapons,
     Armour,
     Comestibles,
     Wands,
     Rings,
+    Amulets,
     Potions,
     Scrolls,
     Spellbooks,
@@ -225,97 +255,275 @@ enum ItemClass {
     GemsnStones
 }
 
-enum BUC {
+impl ItemClass {
+    pub fn all() -> [ItemClass; 12] {
+        [ItemClass::Coins, ItemClass::Weapons, ItemClass::Armour,
+         ItemClass::Comestibles, ItemClass::Wands, ItemClass::Rings,
+         ItemClass::Amulets, ItemClass::Potions, ItemClass::Scrolls,
+         ItemClass::Spellbooks, ItemClass::Tools, ItemClass::GemsnStones]
+    }
+
+    // accepts the word NetHack uses for a single object of the class,
+    // e.g. "wand" in "an oak wand", as well as the class name itself
+    pub fn from_name(name: &str) -> Option<Self> {
+        match name.to_lowercase().as_str() {
+            "coin" | "coins" | "gold" => Some(ItemClass::Coins),
+            "weapon" | "weapons" => Some(ItemClass::Weapons),
+            "armor" | "armour" => Some(ItemClass::Armour),
+            "food" | "comestible" | "comestibles" => Some(ItemClass::Comestibles),
+            "wand" | "wands" => Some(ItemClass::Wands),
+            "ring" | "rings" => Some(ItemClass::Rings),
+            "amulet" | "amulets" => Some(ItemClass::Amulets),
+            "potion" | "potions" => Some(ItemClass::Potions),
+            "scroll" | "scrolls" => Some(ItemClass::Scrolls),
+            "spellbook" | "spellbooks" => Some(ItemClass::Spellbooks),
+            "tool" | "tools" => Some(ItemClass::Tools),
+            "gem" | "gems" | "stone" | "stones" | "gems/stones" => Some(ItemClass::GemsnStones),
+            _ => None
+        }
+    }
+
+    pub fn glyph(self) -> char {
+        match self {
+            ItemClass::Coins => '$',
+            ItemClass::Weapons => ')',
+            ItemClass::Armour => '[',
+            ItemClass::Comestibles => '%',
+            ItemClass::Wands => '/',
+            ItemClass::Rings => '=',
+            ItemClass::Amulets => '"',
+            ItemClass::Potions => '!',
+            ItemClass::Scrolls => '?',
+            ItemClass::Spellbooks => '+',
+            ItemClass::Tools => '(',
+            ItemClass::GemsnStones => '*'
+        }
+    }
+}
+
+#[allow(clippy::upper_case_acronyms)]
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum BUC {
     Blessed,
     Uncursed,
     Cursed
 }
 
-enum WearType {
+impl BUC {
+    pub fn from_name(name: &str) -> Option<Self> {
+        match name.to_lowercase().as_str() {
+            "blessed" | "holy" => Some(BUC::Blessed),
+            "uncursed" => Some(BUC::Uncursed),
+            "cursed" | "unholy" => Some(BUC::Cursed),
+            _ => None
+        }
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum WearType {
     Corroded,
     Rusty,
     Burnt,
     Rotted
 }
 
-enum WearExtent {
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum WearExtent {
     None,
     Some,
     Very,
     Thoroughly
 }
 
-struct Wear {
-    e_type: WearType,
-    e_extent: WearExtent
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Wear {
+    pub e_type: WearType,
+    pub e_extent: WearExtent
+}
+
+// the erodeproof word NetHack shows once the property is known, a
+// crysknife's is "fixed" rather than any kind of proofing
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Proofing {
+    Rustproof,
+    Fireproof,
+    Corrodeproof,
+    Rotproof,
+    Fixed
 }
 
-struct NHInvItem {
-    item: ItemClass,
-    inventory_letter: char, // strictly speaking A-Z
-    beatitude: Option<BUC>,
-    erosion: Wear,
-    charges: Option<u32>,
-    enchantment: Option<u32>,
-    fooproofed: bool,
-    greased: bool,
-    description: String,
-    name: String
+#[derive(Clone, Debug, PartialEq)]
+pub struct NHInvItem {
+    pub item: Option<ItemClass>, // None when the description doesn't give it away
+    pub inventory_letter: Option<char>, // strictly speaking A-Z, None on the floor
+    pub quantity: u32,
+    pub beatitude: Option<BUC>,
+    pub erosion: Vec<Wear>,
+    pub recharged: Option<u32>,
+    pub charges: Option<i32>,
+    pub enchantment: Option<i32>,
+    pub fooproofed: Option<Proofing>,
+    pub greased: bool,
+    pub modifiers: Vec<String>, // "empty", "partly eaten", "poisoned" and so on
+    pub description: String, // the object itself, e.g. "oak wand" or "wand of striking"
+    pub called: Option<String>,
+    pub named: Option<String>,
+    pub status: Vec<String> // trailing notes like "being worn", without the parentheses
 }
 
-struct NetHackData {
+impl NHInvItem {
+    pub fn new(description: &str) -> Self {
+        NHInvItem {
+            item: None,
+            inventory_letter: None,
+            quantity: 1,
+            beatitude: None,
+            erosion: Vec::new(),
+            recharged: None,
+            charges: None,
+            enchantment: None,
+            fooproofed: None,
+            greased: false,
+            modifiers: Vec::new(),
+            description: description.to_string(),
+            called: None,
+            named: None,
+            status: Vec::new()
+        }
+    }
+}
+
+pub struct NetHackData {
     windows: Vec<SubWindow>,
-    //level_map: NHMap,
+    level_map: NHMap,
     inventory: NHInv,
-    status: NHStats
+    status: NHStats,
+    status_read: bool
 }
 
 impl NetHackData {
     pub fn new() -> Self {
         NetHackData {
             windows: Vec::new(),
+            level_map: NHMap::new(),
             inventory: Vec::new(),
-            status: NHStats::new()
+            status: NHStats::new(),
+            status_read: false
         }
     }
 
     pub fn update(&mut self, term: &GameScreen) -> Result<()> {
+        let dlvl = self.status.dlvl;
         self.windows.clear();
         let sub_windows = term.get_subwindows()?;
         for window in sub_windows {
             self.windows.push(window);
         }
 
-        for window in self.windows.clone() {
+        for (window_nr, window) in (1..).zip(self.windows.clone()) {
             let (height, width) = window.get_size();
-            write!(stderr(), "win size: {} by {}\n", height, width);
+            trace!("window={} height={} width={}", window_nr, height, width);
+            for line in window.get_lines()? {
+                trace!("window={} line={:?}", window_nr, line);
+            }
             if height == 2 {
                 // statusline!
-                self.status.read_statusline(&window);
-                write!(stderr(), "{:?}\n", self.status);
+                self.read_status(&window)?;
             }
         }
 
+        // the tty interface just has the bottom two lines of the screen
+        let rows = term.get_rows();
+        if rows.len() >= 2 && rows[rows.len() - 1].iter().collect::<String>().contains("Dlvl:") {
+            self.read_status(&SubWindow::from_rows(&rows, rows.len() - 2, 2))?;
+        }
+
+        // a new level means starting the map over
+        if self.status.dlvl != dlvl {
+            self.level_map = NHMap::new();
+        }
+        // menus and the pile window are drawn over the map, and their
+        // text would be taken for walls and floor
+        let covered = read_menu(&rows).is_some()
+            || rows.iter().any(|row| row.iter().collect::<String>().contains("--More--"));
+        if covered {
+            self.level_map.hide_player();
+        } else {
+            let origin = NHMap::find_origin(&self.windows);
+            self.level_map.update(&term.get_cells(), origin, term.get_cursor());
+        }
+
         Ok(())
     }
 
-    pub fn debug(&self, stderr: &mut Stderr) {
-        let mut window_nr = 1;
+    // the name and title only turn up once the game is under way
+    fn read_status(&mut self, window: &SubWindow) -> Result<()> {
+        self.status.read_statusline(window)?;
+        if window.get_lines()?.iter().any(|line| line.contains(" the ")) {
+            self.status_read = true;
+        }
+        trace!("status={:?}", format!("{:?}", self.status));
+        Ok(())
+    }
+
+    // rank title and alignment off the statusline, once there's been one
+    pub fn get_character(&self) -> Option<(&str, Align)> {
+        if !self.status_read {
+            return None;
+        }
+        match self.status.rank {
+            Class::Rank(ref rank) => Some((rank.as_str(), self.status.align)),
+            Class::Polyform(_) => None
+        }
+    }
+
+    // the statusline numbers, once there's been one
+    pub fn get_dlvl(&self) -> Option<u32> {
+        self.status_read.then_some(self.status.dlvl)
+    }
+
+    pub fn get_hp(&self) -> Option<(u32, u32)> {
+        self.status_read.then_some((self.status.hp, self.status.maxhp))
+    }
+
+    pub fn get_turns(&self) -> Option<u32> {
+        self.status.turns.filter(|_| self.status_read)
+    }
 
-        for win in self.windows.clone() {
-            write!(stderr, "this is the {}th window\n", window_nr);
-            if let Ok(line_vec) = win.get_lines() {
-                for line in line_vec {
-                    write!(stderr, "{}\n", line);
+    pub fn get_map(&self) -> &NHMap {
+        &self.level_map
+    }
+
+    // what a look said the player is standing on, the map only ever
+    // sees the '@' there
+    pub fn note_terrain(&mut self, terrain: Terrain) {
+        if let Some(square) = self.level_map.player() {
+            self.level_map.set_terrain(square, terrain);
+        }
+    }
+
+    // fills in the inventory from one page of the 'i' menu, called
+    // once per page so entries are keyed on their letter
+    pub fn read_inventory(&mut self, menu: &Menu) {
+        for entry in menu.entries.iter() {
+            if let Some(ref item) = entry.item {
+                let mut item = item.clone();
+                item.inventory_letter = Some(entry.letter);
+                if entry.class.is_some() {
+                    item.item = entry.class;
                 }
+                self.inventory.retain(|i| i.inventory_letter != Some(entry.letter));
+                self.inventory.push(item);
             }
-            window_nr += 1;
         }
     }
 
-    
-
-    //try_inventory(&mut self, window: &SubWindow) -> Result<()> {
+    pub fn clear_inventory(&mut self) {
+        self.inventory.clear();
+    }
 
-    //}
-}
\ No newline at end of file
+    pub fn get_inventory(&self) -> &NHInv {
+        &self.inventory
+    }
+}
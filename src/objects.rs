@@ -0,0 +1,433 @@
+use std::fmt;
+use crate::nethack::{ItemClass, NHInvItem, Proofing, Wear, WearExtent, WearType, BUC};
+
+// modifiers NetHack puts in front of an object besides BUC, erosion and
+// enchantment, "empty" goes before the BUC status and the rest after it
+const LEADING_MODIFIERS: [&str; 1] = ["empty"];
+const MODIFIERS: [&str; 7] = ["locked", "unlocked", "broken", "poisoned", "diluted", "partly eaten", "partly used"];
+
+// descriptions that contain "of" without the object being identified
+const UNIDENTIFIED_WITH_OF: [&str; 1] = ["piece of cloth"];
+
+// looked for anywhere in the description before falling back on the
+// object's head word, mostly things whose head word would mislead
+const CLASS_PHRASES: [(&str, ItemClass); 12] = [
+    ("gold piece", ItemClass::Coins),
+    ("amulet of yendor", ItemClass::Amulets),
+    ("looking glass", ItemClass::Tools),
+    ("glass", ItemClass::GemsnStones),
+    ("piece of cloth", ItemClass::Armour),
+    ("meat ring", ItemClass::Comestibles),
+    ("chunk of meat", ItemClass::Comestibles),
+    ("clove of garlic", ItemClass::Comestibles),
+    ("sprig of wolfsbane", ItemClass::Comestibles),
+    ("lump of royal jelly", ItemClass::Comestibles),
+    ("broad pick", ItemClass::Weapons),
+    ("pick-axe", ItemClass::Tools)
+];
+
+const CLASS_WORDS: [(&str, ItemClass); 11] = [
+    ("wand", ItemClass::Wands),
+    ("ring", ItemClass::Rings),
+    ("amulet", ItemClass::Amulets),
+    ("potion", ItemClass::Potions),
+    ("scroll", ItemClass::Scrolls),
+    ("spellbook book novel", ItemClass::Spellbooks),
+    ("gem stone luckstone loadstone touchstone flint rock diamond ruby jacinth \
+      sapphire opal emerald turquoise citrine aquamarine amber topaz jet \
+      chrysoberyl garnet amethyst jasper fluorite obsidian agate jade",
+     ItemClass::GemsnStones),
+    ("sword broadsword runesword dagger crysknife athame knife stiletto scalpel axe \
+      battle-axe mattock quarterstaff staff bow arrow yumi ya crossbow bolt \
+      sling dart shuriken star boomerang spear javelin trident lance mace \
+      flail hammer club aklys hose whip bullwhip scimitar saber katana tsurugi \
+      polearm poleaxe cleaver partisan ranseur spetum glaive halberd bardiche \
+      voulge fauchard guisarme bill-guisarme corbin hook",
+     ItemClass::Weapons),
+    ("armor mail mithril-coat scales helm helmet hat cap fedora cornuthaum \
+      kabuto cloak cape cope robe apron smock wrapping jacket shirt t-shirt \
+      shield gloves gauntlets boots shoes",
+     ItemClass::Armour),
+    ("bag sack box chest key pick card candle lamp lantern whistle flute horn \
+      harp drum bugle bell mirror blindfold towel saddle leash stethoscope kit \
+      opener can figurine marker ball lenses camera mine beartrap candelabrum",
+     ItemClass::Tools),
+    ("corpse egg tin ration wafer apple orange pear melon banana carrot mold \
+      cookie pie bar meatball stick pancake frond leaf glob",
+     ItemClass::Comestibles)
+];
+
+fn lookup_class_word(word: &str) -> Option<ItemClass> {
+    let mut candidates = vec![word.to_string()];
+    if let Some(w) = word.strip_suffix("ves") {
+        candidates.push(format!("{}fe", w));
+    }
+    if let Some(w) = word.strip_suffix("es") {
+        candidates.push(w.to_string());
+    }
+    if let Some(w) = word.strip_suffix('s') {
+        candidates.push(w.to_string());
+    }
+    for candidate in candidates {
+        for (words, class) in CLASS_WORDS.iter() {
+            if words.split_whitespace().any(|w| w == candidate) {
+                return Some(*class);
+            }
+        }
+    }
+    None
+}
+
+// works out the class from the description alone, which can't be done
+// for every object, but the word NetHack builds the name around is
+// usually enough: "wand" in "wand of striking", "stone" in "gray stone"
+pub fn classify(description: &str) -> Option<ItemClass> {
+    let d = description.to_lowercase();
+    for (phrase, class) in CLASS_PHRASES.iter() {
+        if d.contains(phrase) {
+            return Some(*class);
+        }
+    }
+
+    let d = d.strip_prefix("pair of ").or_else(|| d.strip_prefix("pairs of ")).unwrap_or(&d);
+    let head = d.split(" of ").next().unwrap_or(d);
+    let head = head.split(" labeled ").next().unwrap_or(head);
+    if let Some(class) = head.split_whitespace().last().and_then(lookup_class_word) {
+        return Some(class);
+    }
+    d.split_whitespace().filter_map(lookup_class_word).next()
+}
+
+fn parse_charges(s: &str) -> Option<(u32, i32)> {
+    let mut split = s.splitn(2, ':');
+    let recharged = split.next()?.parse::<u32>().ok()?;
+    let charges = split.next()?.parse::<i32>().ok()?;
+    Some((recharged, charges))
+}
+
+fn parse_enchantment(s: &str) -> Option<i32> {
+    if s.len() < 2 || !(s.starts_with('+') || s.starts_with('-')) {
+        return None;
+    }
+    s.parse::<i32>().ok()
+}
+
+fn parse_wear_type(s: &str) -> Option<WearType> {
+    match s {
+        "corroded" => Some(WearType::Corroded),
+        "rusty" => Some(WearType::Rusty),
+        "burnt" => Some(WearType::Burnt),
+        "rotted" => Some(WearType::Rotted),
+        _ => None
+    }
+}
+
+fn parse_proofing(s: &str) -> Option<Proofing> {
+    match s {
+        "rustproof" => Some(Proofing::Rustproof),
+        "fireproof" => Some(Proofing::Fireproof),
+        "corrodeproof" => Some(Proofing::Corrodeproof),
+        "rotproof" => Some(Proofing::Rotproof),
+        "fixed" => Some(Proofing::Fixed),
+        _ => None
+    }
+}
+
+// parses an object the way NetHack's doname() writes it, optionally
+// with the inventory letter in front:
+//
+//     c - an uncursed very rusty rustproof +1 long sword named Foo (weapon in hand)
+pub fn parse_object(text: &str) -> Option<NHInvItem> {
+    let mut rest = text.trim();
+    let mut item = NHInvItem::new("");
+
+    let mut chars = rest.chars();
+    if let (Some(letter), Some(' '), Some('-'), Some(' ')) = (chars.next(), chars.next(), chars.next(), chars.next()) {
+        item.inventory_letter = Some(letter);
+        rest = &rest[4..];
+    }
+
+    // trailing parentheticals, last first
+    while rest.ends_with(')') {
+        let open = rest.rfind(" (")?;
+        let inner = &rest[open + 2 .. rest.len() - 1];
+        match parse_charges(inner) {
+            Some((recharged, charges)) if item.charges.is_none() => {
+                item.recharged = Some(recharged);
+                item.charges = Some(charges);
+            },
+            _ => item.status.insert(0, inner.to_string())
+        }
+        rest = &rest[..open];
+    }
+
+    if let Some(i) = rest.find(" named ") {
+        item.named = Some(rest[i + 7 ..].to_string());
+        rest = &rest[..i];
+    }
+    if let Some(i) = rest.find(" called ") {
+        item.called = Some(rest[i + 8 ..].to_string());
+        rest = &rest[..i];
+    }
+
+    let words: Vec<&str> = rest.split(' ').filter(|w| !w.is_empty()).collect();
+    let mut i = 0;
+    match words.first() {
+        Some(&"a") | Some(&"an") | Some(&"the") => i += 1,
+        Some(w) => if let Ok(n) = w.parse::<u32>() {
+            item.quantity = n;
+            i += 1;
+        },
+        None => return None
+    }
+
+    while i + 1 < words.len() {
+        let word = words[i];
+        let pair = format!("{} {}", word, words[i + 1]);
+        if let Some(buc) = BUC::from_name(word).filter(|_| word != "holy" && word != "unholy") {
+            item.beatitude = Some(buc);
+        } else if word == "greased" {
+            item.greased = true;
+        } else if LEADING_MODIFIERS.contains(&word) || MODIFIERS.contains(&word) {
+            item.modifiers.push(word.to_string());
+        } else if MODIFIERS.contains(&pair.as_str()) {
+            item.modifiers.push(pair);
+            i += 1;
+        } else if let Some(e_type) = parse_wear_type(word) {
+            item.erosion.push(Wear { e_type, e_extent: WearExtent::Some });
+        } else if let (Some(e_extent), Some(e_type)) = (match word {
+            "very" => Some(WearExtent::Very),
+            "thoroughly" => Some(WearExtent::Thoroughly),
+            _ => None
+        }, parse_wear_type(words[i + 1])) {
+            item.erosion.push(Wear { e_type, e_extent });
+            i += 1;
+        } else if let Some(proofing) = parse_proofing(word) {
+            item.fooproofed = Some(proofing);
+        } else if let Some(n) = parse_enchantment(word) {
+            item.enchantment = Some(n);
+        } else {
+            break;
+        }
+        i += 1;
+    }
+
+    if i >= words.len() {
+        return None;
+    }
+    item.description = words[i..].join(" ");
+    item.item = classify(&item.description);
+    Some(item)
+}
+
+// NetHack's just_an()
+fn article_for(word: &str) -> &'static str {
+    let lower = word.to_lowercase();
+    let exceptions = ["one-", "eucalyptus", "unicorn", "uranium", "useful"];
+    if lower.len() == 1 || (lower.starts_with(['a', 'e', 'i', 'o', 'u'])
+                            && !exceptions.iter().any(|e| lower.starts_with(e))) {
+        "an"
+    } else {
+        "a"
+    }
+}
+
+impl NHInvItem {
+    // the identified object type, only available when NetHack names it
+    // outright as in "wand of striking" or "ring of conflict"
+    pub fn identified_name(&self) -> Option<&str> {
+        let d = self.description.as_str();
+        let lower = d.to_lowercase();
+        let unpaired = lower.strip_prefix("pair of ").or_else(|| lower.strip_prefix("pairs of ")).unwrap_or(&lower);
+        if unpaired.contains(" of ") && !UNIDENTIFIED_WITH_OF.iter().any(|u| unpaired.contains(u)) {
+            Some(d)
+        } else {
+            None
+        }
+    }
+
+    // what an unidentified object looks like, e.g. "oak wand", which
+    // is hidden once the player has called the object type something
+    pub fn appearance(&self) -> Option<&str> {
+        if self.called.is_some() || self.identified_name().is_some() {
+            None
+        } else {
+            Some(&self.description)
+        }
+    }
+}
+
+fn wear_words(wear: &Wear) -> String {
+    let extent = match wear.e_extent {
+        WearExtent::Very => "very ",
+        WearExtent::Thoroughly => "thoroughly ",
+        _ => ""
+    };
+    let e_type = match wear.e_type {
+        WearType::Corroded => "corroded",
+        WearType::Rusty => "rusty",
+        WearType::Burnt => "burnt",
+        WearType::Rotted => "rotted"
+    };
+    format!("{}{}", extent, e_type)
+}
+
+impl fmt::Display for NHInvItem {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let mut words: Vec<String> = Vec::new();
+        for modifier in self.modifiers.iter().filter(|m| LEADING_MODIFIERS.contains(&m.as_str())) {
+            words.push(modifier.clone());
+        }
+        match self.beatitude {
+            Some(BUC::Blessed) => words.push("blessed".to_string()),
+            Some(BUC::Uncursed) => words.push("uncursed".to_string()),
+            Some(BUC::Cursed) => words.push("cursed".to_string()),
+            None => ()
+        }
+        if self.greased {
+            words.push("greased".to_string());
+        }
+        for modifier in self.modifiers.iter().filter(|m| !LEADING_MODIFIERS.contains(&m.as_str())) {
+            words.push(modifier.clone());
+        }
+        for wear in self.erosion.iter().filter(|w| w.e_extent != WearExtent::None) {
+            words.push(wear_words(wear));
+        }
+        match self.fooproofed {
+            Some(Proofing::Rustproof) => words.push("rustproof".to_string()),
+            Some(Proofing::Fireproof) => words.push("fireproof".to_string()),
+            Some(Proofing::Corrodeproof) => words.push("corrodeproof".to_string()),
+            Some(Proofing::Rotproof) => words.push("rotproof".to_string()),
+            Some(Proofing::Fixed) => words.push("fixed".to_string()),
+            None => ()
+        }
+        if let Some(n) = self.enchantment {
+            words.push(format!("{:+}", n));
+        }
+        words.push(self.description.clone());
+
+        if let Some(letter) = self.inventory_letter {
+            write!(f, "{} - ", letter)?;
+        }
+        if self.quantity != 1 {
+            write!(f, "{} ", self.quantity)?;
+        } else if self.description.starts_with(char::is_uppercase) && self.description.chars().nth(1) != Some('-') {
+            // artifacts and unique items, but not a K-ration or T-shirt
+            write!(f, "the ")?;
+        } else {
+            write!(f, "{} ", article_for(&words[0]))?;
+        }
+        write!(f, "{}", words.join(" "))?;
+        if let Some(ref called) = self.called {
+            write!(f, " called {}", called)?;
+        }
+        if let Some(ref named) = self.named {
+            write!(f, " named {}", named)?;
+        }
+        if let (Some(recharged), Some(charges)) = (self.recharged, self.charges) {
+            write!(f, " ({}:{})", recharged, charges)?;
+        }
+        for status in self.status.iter() {
+            write!(f, " ({})", status)?;
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // descriptions as NetHack 3.6 prints them, each one has to come back
+    // out of Display exactly as it went into the parser
+    const CORPUS: [&str; 30] = [
+        "an oak wand",
+        "a wand of striking (0:4)",
+        "a blessed wand of wishing (1:-1)",
+        "a wand called sleep",
+        "2 gray stones",
+        "a white gem",
+        "an uncursed luckstone",
+        "a cursed ring named Frodo",
+        "a blessed +2 ring of protection",
+        "an uncursed onyx amulet",
+        "3 uncursed scrolls labeled KIRJE",
+        "an unlabeled scroll",
+        "2 cursed potions of water",
+        "a bubbly potion",
+        "an uncursed diluted smoky potion",
+        "an orange spellbook",
+        "a - an uncursed +1 long sword (weapon in hand)",
+        "b - 12 +0 daggers (in quiver)",
+        "c - an uncursed very rusty rustproof +0 long sword",
+        "d - a blessed greased thoroughly corroded +2 elven mithril-coat (being worn)",
+        "e - an uncursed burnt very rotted +0 leather cloak",
+        "f - a blessed fixed +3 crysknife",
+        "g - an empty uncursed bag of holding",
+        "h - an uncursed partly eaten food ration",
+        "i - an uncursed magic marker (0:53)",
+        "j - a blessed oil lamp (lit)",
+        "k - an uncursed +0 pair of hard shoes (being worn)",
+        "$ - 35 gold pieces",
+        "a lichen corpse",
+        "the blessed Orb of Fate"
+    ];
+
+    #[test]
+    fn round_trip_corpus() {
+        for text in CORPUS.iter() {
+            let item = parse_object(text).unwrap_or_else(|| panic!("failed to parse {:?}", text));
+            assert_eq!(&item.to_string(), text);
+        }
+    }
+
+    #[test]
+    fn parses_fields() {
+        let item = parse_object("c - 2 cursed greased very rusty rustproof -1 arrows named foo (in quiver)").unwrap();
+        assert_eq!(item.inventory_letter, Some('c'));
+        assert_eq!(item.quantity, 2);
+        assert_eq!(item.beatitude, Some(BUC::Cursed));
+        assert!(item.greased);
+        assert_eq!(item.erosion, vec![Wear { e_type: WearType::Rusty, e_extent: WearExtent::Very }]);
+        assert_eq!(item.fooproofed, Some(Proofing::Rustproof));
+        assert_eq!(item.enchantment, Some(-1));
+        assert_eq!(item.description, "arrows");
+        assert_eq!(item.item, Some(ItemClass::Weapons));
+        assert_eq!(item.named.as_deref(), Some("foo"));
+        assert_eq!(item.status, vec!["in quiver".to_string()]);
+    }
+
+    #[test]
+    fn appearance_and_identity() {
+        let oak = parse_object("an oak wand").unwrap();
+        assert_eq!(oak.item, Some(ItemClass::Wands));
+        assert_eq!(oak.appearance(), Some("oak wand"));
+        assert_eq!(oak.identified_name(), None);
+
+        let striking = parse_object("a wand of striking (0:4)").unwrap();
+        assert_eq!(striking.identified_name(), Some("wand of striking"));
+        assert_eq!(striking.appearance(), None);
+        assert_eq!((striking.recharged, striking.charges), (Some(0), Some(4)));
+
+        let called = parse_object("a wand called sleep").unwrap();
+        assert_eq!(called.item, Some(ItemClass::Wands));
+        assert_eq!(called.called.as_deref(), Some("sleep"));
+        assert_eq!(called.appearance(), None);
+
+        let cloth = parse_object("a piece of cloth").unwrap();
+        assert_eq!(cloth.item, Some(ItemClass::Armour));
+        assert_eq!(cloth.appearance(), Some("piece of cloth"));
+    }
+
+    #[test]
+    fn classifies_without_class_word() {
+        assert_eq!(classify("gray stones"), Some(ItemClass::GemsnStones));
+        assert_eq!(classify("scrolls labeled FOO"), Some(ItemClass::Scrolls));
+        assert_eq!(classify("pair of hard shoes"), Some(ItemClass::Armour));
+        assert_eq!(classify("bag of tricks"), Some(ItemClass::Tools));
+        assert_eq!(classify("worthless piece of blue glass"), Some(ItemClass::GemsnStones));
+        assert_eq!(classify("knives"), Some(ItemClass::Weapons));
+        assert_eq!(classify("tin of newt meat"), Some(ItemClass::Comestibles));
+        assert_eq!(classify("heap of fluff"), None);
+    }
+}
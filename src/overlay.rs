@@ -0,0 +1,13 @@
+use std::io::{Result as ioResult, Write};
+use termion::terminal_size;
+
+// a label in the top right corner of the user's terminal, written past
+// the emulator so the bot never sees it; NetHack draws over it all the
+// time, so it gets put back after every frame
+pub fn draw<W: Write>(out: &mut W, label: &str) -> ioResult<()> {
+    let (cols, _) = terminal_size()?;
+    let col = (cols as usize).saturating_sub(label.len() + 2) + 1;
+    // save the cursor, draw in reverse video, put everything back
+    write!(out, "\x1b7\x1b[1;{}H\x1b[7m {} \x1b[0m\x1b8", col, label)?;
+    out.flush()
+}
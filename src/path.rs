@@ -0,0 +1,230 @@
+use std::collections::VecDeque;
+use crate::map::{MapCell, NHMap, Terrain, MAP_COLS, MAP_ROWS};
+
+const DIRECTIONS: [((i32, i32), char); 8] = [
+    ((-1, -1), 'y'), ((-1, 0), 'k'), ((-1, 1), 'u'),
+    ((0, -1), 'h'),                  ((0, 1), 'l'),
+    ((1, -1), 'b'),  ((1, 0), 'j'),  ((1, 1), 'n')
+];
+
+pub fn direction_key(dy: i32, dx: i32) -> Option<char> {
+    DIRECTIONS.iter().find(|(d, _)| *d == (dy.signum(), dx.signum())).map(|(_, key)| *key)
+}
+
+// monsters and boulders are in the way for now, the path gets worked
+// out again every frame so a pet moving off doesn't block for long;
+// closed doors are let through, it's up to the walker to open them
+fn passable(map: &NHMap, y: usize, x: usize) -> bool {
+    match map.cell(y, x) {
+        MapCell::Monster(_) | MapCell::Boulder => false,
+        _ => map.terrain(y, x).is_walkable() || map.terrain(y, x) == Terrain::ClosedDoor
+    }
+}
+
+fn has_door(map: &NHMap, (y, x): (usize, usize)) -> bool {
+    matches!(map.terrain(y, x), Terrain::OpenDoor | Terrain::ClosedDoor)
+}
+
+// NetHack won't let anything move diagonally into or out of a doorway
+// that has a door in it
+fn diagonal_ok(map: &NHMap, from: (usize, usize), to: (usize, usize)) -> bool {
+    !has_door(map, from) && !has_door(map, to)
+}
+
+// breadth first search from `from` to the nearest square `is_goal`
+// accepts, every step costs the same so this finds a shortest path;
+// the result is the list of squares walked through, ending at the goal
+pub fn find_path<F: Fn(usize, usize) -> bool>(map: &NHMap, from: (usize, usize), is_goal: F) -> Option<Vec<(usize, usize)>> {
+    let mut came_from: Vec<Vec<Option<(usize, usize)>>> = vec![vec![None; MAP_COLS]; MAP_ROWS];
+    let mut queue = VecDeque::new();
+    came_from[from.0][from.1] = Some(from);
+    queue.push_back(from);
+
+    while let Some((y, x)) = queue.pop_front() {
+        if (y, x) != from && is_goal(y, x) {
+            let mut path = vec![(y, x)];
+            let mut square = (y, x);
+            while let Some(prev) = came_from[square.0][square.1] {
+                if prev == from {
+                    break;
+                }
+                path.push(prev);
+                square = prev;
+            }
+            path.reverse();
+            return Some(path);
+        }
+
+        for ((dy, dx), _) in DIRECTIONS.iter() {
+            let (ny, nx) = (y as i32 + dy, x as i32 + dx);
+            if ny < 0 || nx < 0 || ny >= MAP_ROWS as i32 || nx >= MAP_COLS as i32 {
+                continue;
+            }
+            let next = (ny as usize, nx as usize);
+            if came_from[next.0][next.1].is_some() || !passable(map, next.0, next.1) {
+                continue;
+            }
+            if *dy != 0 && *dx != 0 && !diagonal_ok(map, (y, x), next) {
+                continue;
+            }
+            came_from[next.0][next.1] = Some((y, x));
+            queue.push_back(next);
+        }
+    }
+    None
+}
+
+// movement keys that walk the path from `from`
+pub fn path_keys(from: (usize, usize), path: &[(usize, usize)]) -> String {
+    let mut keys = String::new();
+    let mut square = from;
+    for next in path.iter() {
+        let (dy, dx) = (next.0 as i32 - square.0 as i32, next.1 as i32 - square.1 as i32);
+        if let Some(key) = direction_key(dy, dx) {
+            keys.push(key);
+        }
+        square = *next;
+    }
+    keys
+}
+
+// keys for the travel command: '_' brings up the position prompt, '@'
+// puts the cursor on the player wherever the last travel left it, the
+// capital direction keys jump 8 squares and '.' picks the square
+pub fn travel_keys(from: (usize, usize), to: (usize, usize)) -> String {
+    let mut keys = String::from("_@");
+    let (mut dy, mut dx) = (to.0 as i32 - from.0 as i32, to.1 as i32 - from.1 as i32);
+    while dy != 0 || dx != 0 {
+        let (sy, sx) = (dy.signum(), dx.signum());
+        // diagonals first while both offsets last, then straight on
+        let remaining = if sy != 0 && sx != 0 { dy.abs().min(dx.abs()) } else { dy.abs().max(dx.abs()) };
+        let key = match direction_key(sy, sx) {
+            Some(key) => key,
+            None => break
+        };
+        let (count, key) = if remaining >= 8 { (8, key.to_ascii_uppercase()) } else { (1, key) };
+        keys.push(key);
+        dy -= sy * count;
+        dx -= sx * count;
+    }
+    keys.push('.');
+    keys
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use terminal_emulator::ansi::{Color, NamedColor};
+    use terminal_emulator::term::cell::Cell;
+
+    // a map drawn the way the tty interface does from the top left of
+    // the map area, with the player's square as the cursor; '+' and '\''
+    // are a closed and an open door, which NetHack draws in brown
+    fn map_from(lines: &[&str]) -> NHMap {
+        let brown = Color::Named(NamedColor::Yellow);
+        let plain = Color::Named(NamedColor::Foreground);
+        let mut cursor = (0, 0);
+        let mut screen = vec![vec![Cell::default(); MAP_COLS]; MAP_ROWS];
+        for (y, line) in lines.iter().enumerate() {
+            for (x, c) in line.chars().enumerate() {
+                screen[y][x] = match c {
+                    '+' => Cell::new('+', brown, plain),
+                    '\'' => Cell::new('|', brown, plain),
+                    c => Cell::new(c, plain, plain)
+                };
+                if c == '@' {
+                    cursor = (y, x);
+                }
+            }
+        }
+        let mut map = NHMap::new();
+        map.update(&screen, (0, 0), cursor);
+        map
+    }
+
+    fn path_to_wand(lines: &[&str]) -> Option<Vec<(usize, usize)>> {
+        let map = map_from(lines);
+        find_path(&map, map.player().unwrap(), |y, x| map.cell(y, x) == MapCell::Item(crate::nethack::ItemClass::Wands))
+    }
+
+    #[test]
+    fn travel_keys_move_the_cursor() {
+        let cases = [
+            ((5, 5), (5, 5), "_@."),
+            ((0, 0), (0, 3), "_@lll."),
+            ((0, 0), (2, 4), "_@nnll."),
+            ((0, 0), (0, 8), "_@L."),
+            ((0, 0), (0, 19), "_@LLlll."),
+            ((10, 10), (0, 0), "_@Yyy."),
+            ((0, 0), (9, 20), "_@NnLlll."),
+            ((5, 30), (5, 2), "_@HHHhhhh."),
+            ((20, 3), (2, 5), "_@uuKK.")
+        ];
+        for (from, to, keys) in cases.iter() {
+            assert_eq!(travel_keys(*from, *to), *keys, "from {:?} to {:?}", from, to);
+        }
+    }
+
+    #[test]
+    fn shortest_path_goes_diagonally() {
+        let path = path_to_wand(&[
+            "------",
+            "|@...|",
+            "|....|",
+            "|.../|",
+            "------"
+        ]).unwrap();
+        assert_eq!(path.len(), 3);
+        assert_eq!(path.last(), Some(&(3, 4)));
+    }
+
+    #[test]
+    fn doorways_are_entered_and_left_straight() {
+        let path = path_to_wand(&[
+            "-----  ",
+            "|...|  ",
+            "|..@\'##",
+            "|...|  #",
+            "-----  /"
+        ]).unwrap();
+        assert_eq!(path, [(2, 4), (2, 5), (2, 6), (3, 7), (4, 7)]);
+    }
+
+    #[test]
+    fn closed_doors_are_on_the_way() {
+        let map = map_from(&[
+            "-----  ",
+            "|..@+##/",
+            "-----  "
+        ]);
+        let path = find_path(&map, (1, 3), |y, x| map.cell(y, x) == MapCell::Item(crate::nethack::ItemClass::Wands)).unwrap();
+        assert_eq!(path, [(1, 4), (1, 5), (1, 6), (1, 7)]);
+        assert_eq!(map.terrain(1, 4), Terrain::ClosedDoor);
+    }
+
+    #[test]
+    fn monsters_are_walked_around() {
+        let path = path_to_wand(&[
+            "-----",
+            "|.d.|",
+            "|@d/|",
+            "|...|",
+            "-----"
+        ]).unwrap();
+        assert_eq!(path, [(3, 2), (2, 3)]);
+    }
+
+    #[test]
+    fn walled_off_targets_have_no_path() {
+        assert_eq!(path_to_wand(&[
+            "-----  -----",
+            "|.@.|  |./.|",
+            "-----  -----"
+        ]), None);
+        assert_eq!(path_to_wand(&[
+            "-----",
+            "|@d/|",
+            "-----"
+        ]), None);
+    }
+}
@@ -0,0 +1,129 @@
+use std::error;
+use serde::Deserialize;
+use crate::nethack::{ItemClass, NHInvItem, BUC};
+
+type Result<T> = std::result::Result<T, Box<dyn error::Error>>;
+
+// a rule file is a list of [[target]] tables, e.g.
+//
+//     [[target]]
+//     class = "wand"
+//     appearance = "oak"
+//
+//     [[target]]
+//     class = "ring"
+//     buc = "blessed"
+//
+// every field a target sets has to match, and an item is kept if any
+// of the targets match it
+#[derive(Deserialize)]
+struct RuleFile {
+    #[serde(default)]
+    target: Vec<RuleSpec>
+}
+
+#[derive(Deserialize)]
+#[serde(deny_unknown_fields)]
+struct RuleSpec {
+    class: Option<String>,
+    appearance: Option<String>,
+    buc: Option<String>,
+    name: Option<String>
+}
+
+pub struct Rule {
+    class: Option<ItemClass>,
+    appearance: Option<String>,
+    buc: Option<BUC>,
+    name: Option<String>
+}
+
+impl Rule {
+    fn from_spec(spec: RuleSpec) -> Result<Self> {
+        let class = match spec.class {
+            Some(ref c) => Some(ItemClass::from_name(c)
+                .ok_or(format!("unknown item class in target rule: {}", c))?),
+            None => None
+        };
+        let buc = match spec.buc {
+            Some(ref b) => Some(BUC::from_name(b)
+                .ok_or(format!("unknown BUC status in target rule: {}", b))?),
+            None => None
+        };
+        Ok(Rule {
+            class,
+            appearance: spec.appearance.map(|s| s.to_lowercase()),
+            buc,
+            name: spec.name.map(|s| s.to_lowercase())
+        })
+    }
+
+    pub fn matches(&self, item: &NHInvItem) -> bool {
+        if self.class.is_some() && self.class != item.item {
+            return false;
+        }
+        if self.buc.is_some() && self.buc != item.beatitude {
+            return false;
+        }
+        if let Some(ref appearance) = self.appearance {
+            match item.appearance() {
+                Some(a) if a.to_lowercase().contains(appearance.as_str()) => (),
+                _ => return false
+            }
+        }
+        if let Some(ref name) = self.name {
+            let names = [item.identified_name(), item.called.as_deref(), item.named.as_deref()];
+            if !names.iter().flatten().any(|n| n.to_lowercase().contains(name.as_str())) {
+                return false;
+            }
+        }
+        true
+    }
+}
+
+pub struct TargetRules {
+    rules: Vec<Rule>
+}
+
+impl TargetRules {
+    pub fn parse(text: &str) -> Result<Self> {
+        let file: RuleFile = toml::from_str(text)?;
+        let mut rules = Vec::new();
+        for spec in file.target {
+            rules.push(Rule::from_spec(spec)?);
+        }
+        Ok(TargetRules { rules })
+    }
+
+    pub fn wants(&self, item: &NHInvItem) -> bool {
+        self.rules.iter().any(|rule| rule.matches(item))
+    }
+
+    // object classes worth walking over to, a target with no class
+    // could be any kind of object
+    pub fn classes(&self) -> Vec<ItemClass> {
+        let mut classes = Vec::new();
+        for rule in self.rules.iter() {
+            match rule.class {
+                Some(class) if !classes.contains(&class) => classes.push(class),
+                Some(_) => (),
+                None => return ItemClass::all().to_vec()
+            }
+        }
+        classes
+    }
+}
+
+impl Default for TargetRules {
+    // what titm always hunted for before there were rule files
+    fn default() -> Self {
+        TargetRules {
+            rules: vec![Rule {
+                class: Some(ItemClass::Wands),
+                appearance: None,
+                buc: None,
+                name: None
+            }]
+        }
+    }
+}
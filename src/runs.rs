@@ -0,0 +1,120 @@
+use std::error;
+use std::fs::{self, OpenOptions};
+use std::io::{ErrorKind, Write};
+use std::path::Path;
+use std::time::{SystemTime, UNIX_EPOCH};
+
+type Result<T> = std::result::Result<T, Box<dyn error::Error>>;
+
+// how a game run by the bot came to an end
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Outcome {
+    // the bot gave up on the level and quit, so it's worth another go
+    Rerolled,
+    // a target is in the inventory and the user played on from there
+    Found,
+    // NetHack went away without the bot quitting
+    Ended,
+    // and didn't exit cleanly either
+    Crashed
+}
+
+impl Outcome {
+    pub fn name(self) -> &'static str {
+        match self {
+            Outcome::Rerolled => "rerolled",
+            Outcome::Found => "found",
+            Outcome::Ended => "ended",
+            Outcome::Crashed => "crashed"
+        }
+    }
+
+    fn from_name(name: &str) -> Option<Self> {
+        match name {
+            "rerolled" => Some(Outcome::Rerolled),
+            "found" => Some(Outcome::Found),
+            "ended" => Some(Outcome::Ended),
+            "crashed" => Some(Outcome::Crashed),
+            _ => None
+        }
+    }
+}
+
+// one line per game in the runs file:
+//
+//     <unix time started> <seconds taken> <outcome>
+pub struct RunRecord {
+    pub started: u64,
+    pub seconds: u64,
+    pub outcome: Outcome
+}
+
+impl RunRecord {
+    pub fn new(started: SystemTime, outcome: Outcome) -> Self {
+        RunRecord {
+            started: started.duration_since(UNIX_EPOCH).map_or(0, |d| d.as_secs()),
+            seconds: started.elapsed().map_or(0, |d| d.as_secs()),
+            outcome
+        }
+    }
+
+    fn parse(line: &str) -> Option<Self> {
+        let mut fields = line.split_whitespace();
+        Some(RunRecord {
+            started: fields.next()?.parse().ok()?,
+            seconds: fields.next()?.parse().ok()?,
+            outcome: Outcome::from_name(fields.next()?)?
+        })
+    }
+}
+
+pub fn record(path: &Path, run: &RunRecord) -> Result<()> {
+    let mut file = OpenOptions::new().create(true).append(true).open(path)?;
+    writeln!(file, "{} {} {}", run.started, run.seconds, run.outcome.name())?;
+    Ok(())
+}
+
+// a missing file just means nothing has been run yet
+pub fn load(path: &Path) -> Result<Vec<RunRecord>> {
+    let text = match fs::read_to_string(path) {
+        Ok(text) => text,
+        Err(e) if e.kind() == ErrorKind::NotFound => return Ok(Vec::new()),
+        Err(e) => return Err(e.into())
+    };
+    let mut runs = Vec::new();
+    for (n, line) in (1..).zip(text.lines()) {
+        if line.trim().is_empty() {
+            continue;
+        }
+        runs.push(RunRecord::parse(line)
+            .ok_or(format!("{}:{}: bad run record: {}", path.display(), n, line))?);
+    }
+    Ok(runs)
+}
+
+pub fn summary(runs: &[RunRecord]) -> String {
+    if runs.is_empty() {
+        return "no games recorded".to_string();
+    }
+    let mut text = format!("{} games\n", runs.len());
+    for outcome in [Outcome::Rerolled, Outcome::Found, Outcome::Ended, Outcome::Crashed].iter() {
+        let count = runs.iter().filter(|run| run.outcome == *outcome).count();
+        text.push_str(&format!("{:>8}: {}\n", outcome.name(), count));
+    }
+    let seconds: u64 = runs.iter().map(|run| run.seconds).sum();
+    text.push_str(&format!("average game: {}s, total {}s\n", seconds / runs.len() as u64, seconds));
+    // games it took to get each find, counting the one it was found in
+    let mut since = 0;
+    let mut finds = Vec::new();
+    for run in runs.iter() {
+        since += 1;
+        if run.outcome == Outcome::Found {
+            finds.push(since);
+            since = 0;
+        }
+    }
+    if !finds.is_empty() {
+        text.push_str(&format!("games per find: {}\n", finds.iter().sum::<u32>() / finds.len() as u32));
+    }
+    text
+}
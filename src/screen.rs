@@ -0,0 +1,173 @@
+use std::error;
+use std::io::Write;
+use terminal_emulator::ansi::Processor;
+use terminal_emulator::term::cell::Cell;
+use terminal_emulator::term::{SizeInfo, Term, TermMode};
+
+type Result<T> = std::result::Result<T, Box<dyn error::Error>>;
+
+pub struct GameScreen {
+    term: Term,
+    processor: Processor
+}
+
+impl GameScreen {
+    pub fn new(size_info: SizeInfo) -> Self {
+        GameScreen {
+            term: Term::new(size_info),
+            processor: Processor::new()
+        }
+    }
+
+    pub fn resize(&mut self, size_info: SizeInfo) {
+        self.term.resize(&size_info);
+    }
+
+    pub fn advance<W: Write>(&mut self, byte: u8, writer: &mut W) {
+        self.processor.advance(&mut self.term, byte, writer);
+    }
+
+    pub fn cursor_visible(&self) -> bool {
+        self.term.mode().contains(TermMode::SHOW_CURSOR)
+    }
+
+    // (line, column) of the cursor, which sits on the player whenever
+    // NetHack is waiting for a command
+    pub fn get_cursor(&self) -> (usize, usize) {
+        let point = self.term.cursor().point;
+        (*point.line, *point.col)
+    }
+
+    // copy of the visible grid, so the window finding code doesn't have
+    // to deal with the emulator's index types
+    pub fn get_cells(&self) -> Vec<Vec<Cell>> {
+        let mut rows: Vec<Vec<Cell>> = Vec::new();
+        for cell in self.term.grid().display_iter() {
+            let line = *cell.line;
+            while rows.len() <= line {
+                rows.push(Vec::new());
+            }
+            rows[line].push(*cell);
+        }
+        rows
+    }
+
+    pub fn get_rows(&self) -> Vec<Vec<char>> {
+        self.get_cells().iter()
+            .map(|row| row.iter().map(|cell| cell.c).collect())
+            .collect()
+    }
+
+    pub fn get_subwindows(&self) -> Result<Vec<SubWindow>> {
+        Ok(find_subwindows(&self.get_rows()))
+    }
+}
+
+// the curses interface draws each window inside a box of line drawing
+// characters, the straight runs and the tees and crosses where the
+// boxes meet
+fn is_horizontal(c: char) -> bool {
+    matches!(c, '─' | '┬' | '┴' | '┼')
+}
+
+fn is_vertical(c: char) -> bool {
+    matches!(c, '│' | '├' | '┤' | '┼')
+}
+
+fn char_at(rows: &[Vec<char>], line: usize, col: usize) -> Option<char> {
+    rows.get(line).and_then(|row| row.get(col)).copied()
+}
+
+fn find_box(rows: &[Vec<char>], top: usize, left: usize) -> Option<(usize, usize)> {
+    let mut right = left + 1;
+    while char_at(rows, top, right).is_some_and(is_horizontal) {
+        right += 1;
+    }
+    if char_at(rows, top, right) != Some('┐') {
+        return None;
+    }
+
+    let mut bottom = top + 1;
+    while char_at(rows, bottom, left).is_some_and(is_vertical) {
+        bottom += 1;
+    }
+    if char_at(rows, bottom, left) != Some('└') || char_at(rows, bottom, right) != Some('┘') {
+        return None;
+    }
+    for line in top + 1 .. bottom {
+        if !char_at(rows, line, right).is_some_and(is_vertical) {
+            return None;
+        }
+    }
+    Some((bottom, right))
+}
+
+// a screen row's text from `col` on, without the padding around it or
+// the right edge of a curses window
+pub fn row_text(row: &[char], col: usize) -> String {
+    row.iter().skip(col).collect::<String>()
+        .trim_end().trim_end_matches('│').trim().to_string()
+}
+
+pub fn find_subwindows(rows: &[Vec<char>]) -> Vec<SubWindow> {
+    let mut windows = Vec::new();
+    for (top, row) in rows.iter().enumerate() {
+        for (left, c) in row.iter().enumerate() {
+            if *c != '┌' {
+                continue;
+            }
+            if let Some((bottom, right)) = find_box(rows, top, left) {
+                let lines = rows[top + 1 .. bottom].iter()
+                    .map(|row| row[left + 1 .. right].iter().collect::<String>().trim_end().to_string())
+                    .collect();
+                windows.push(SubWindow {
+                    top: top + 1,
+                    left: left + 1,
+                    height: bottom - top - 1,
+                    width: right - left - 1,
+                    lines
+                });
+            }
+        }
+    }
+    windows
+}
+
+#[derive(Clone, Debug)]
+pub struct SubWindow {
+    top: usize,
+    left: usize,
+    height: usize,
+    width: usize,
+    lines: Vec<String>
+}
+
+impl SubWindow {
+    // the tty interface draws no borders, so a plain run of screen lines
+    // can stand in for a window, e.g. the two status lines at the bottom
+    pub fn from_rows(rows: &[Vec<char>], top: usize, height: usize) -> Self {
+        let lines: Vec<String> = rows.iter().skip(top).take(height)
+            .map(|row| row.iter().collect::<String>().trim_end().to_string())
+            .collect();
+        SubWindow {
+            top,
+            left: 0,
+            height: lines.len(),
+            width: rows.first().map_or(0, |row| row.len()),
+            lines
+        }
+    }
+
+    // position of the first cell inside the border, as (line, column)
+    pub fn get_origin(&self) -> (usize, usize) {
+        (self.top, self.left)
+    }
+
+    pub fn get_size(&self) -> (usize, usize) {
+        (self.height, self.width)
+    }
+
+    pub fn get_lines(&self) -> Result<Vec<String>> {
+        Ok(self.lines.clone())
+    }
+}
@@ -0,0 +1,46 @@
+use std::convert::TryFrom;
+use std::error;
+use std::sync::atomic::{AtomicBool, Ordering};
+use nix::libc;
+use nix::sys::signal::{sigaction, SaFlags, SigAction, SigHandler, SigSet, Signal};
+
+type Result<T> = std::result::Result<T, Box<dyn error::Error>>;
+
+static RESIZED: AtomicBool = AtomicBool::new(false);
+static CHILD_EXITED: AtomicBool = AtomicBool::new(false);
+
+fn flag(signal: Signal) -> Option<&'static AtomicBool> {
+    match signal {
+        Signal::SIGWINCH => Some(&RESIZED),
+        Signal::SIGCHLD => Some(&CHILD_EXITED),
+        _ => None
+    }
+}
+
+extern "C" fn note_signal(signal: libc::c_int) {
+    if let Some(flag) = Signal::try_from(signal).ok().and_then(flag) {
+        flag.store(true, Ordering::SeqCst);
+    }
+}
+
+// the handler only sets a flag, which the event loop picks up once
+// poll() is interrupted; SA_RESTART keeps plain reads and writes from
+// failing with EINTR, and SA_NOCLDSTOP keeps a game stopped with ^Z
+// from looking like one that exited
+pub fn watch(signal: Signal) -> Result<()> {
+    if flag(signal).is_none() {
+        return Err(format!("no flag to note {:?} with", signal).into());
+    }
+    let mut flags = SaFlags::SA_RESTART;
+    if signal == Signal::SIGCHLD {
+        flags |= SaFlags::SA_NOCLDSTOP;
+    }
+    let action = SigAction::new(SigHandler::Handler(note_signal), flags, SigSet::empty());
+    unsafe { sigaction(signal, &action)?; }
+    Ok(())
+}
+
+// whether the signal came in since the last time this was asked
+pub fn take(signal: Signal) -> bool {
+    flag(signal).is_some_and(|flag| flag.swap(false, Ordering::SeqCst))
+}
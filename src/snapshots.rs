@@ -0,0 +1,59 @@
+// golden file tests for the screen parsers: every dump in
+// testdata/screens goes through screen_report() and has to come out
+// the same as the .golden file next to it; run with TITM_BLESS=1 set
+// to write the golden files afresh after checking the changes are good
+//
+// .ansi dumps are raw terminal output, .txt dumps are plain lines of
+// text with the cursor left on the first '@' below the message line,
+// which is where NetHack keeps it
+use std::env;
+use std::fs;
+use std::path::Path;
+use crate::rules::TargetRules;
+use super::screen_report;
+
+const SIZE: (u16, u16) = (80, 24);
+
+fn load_dump(path: &Path) -> Vec<u8> {
+    let bytes = fs::read(path).unwrap();
+    if path.extension().is_some_and(|ext| ext == "ansi") {
+        return bytes;
+    }
+    let text = String::from_utf8(bytes).unwrap();
+    let lines: Vec<&str> = text.lines().collect();
+    let mut out = lines.join("\r\n");
+    let player = lines.iter().enumerate().skip(1)
+        .find_map(|(y, line)| line.chars().position(|c| c == '@').map(|x| (y, x)));
+    if let Some((y, x)) = player {
+        out.push_str(&format!("\x1b[{};{}H", y + 1, x + 1));
+    }
+    out.into_bytes()
+}
+
+#[test]
+fn screen_snapshots() {
+    let dir = Path::new(env!("CARGO_MANIFEST_DIR")).join("testdata/screens");
+    let bless = env::var_os("TITM_BLESS").is_some();
+    let mut dumps: Vec<_> = fs::read_dir(&dir).unwrap()
+        .map(|entry| entry.unwrap().path())
+        .filter(|path| path.extension().is_some_and(|ext| ext == "txt" || ext == "ansi"))
+        .collect();
+    dumps.sort();
+    assert!(!dumps.is_empty(), "no screen dumps in {}", dir.display());
+
+    let mut failures = Vec::new();
+    for dump in dumps.iter() {
+        let report = screen_report(&load_dump(dump), SIZE, &TargetRules::default()).unwrap();
+        let golden = dump.with_extension("golden");
+        if bless {
+            fs::write(&golden, &report).unwrap();
+            continue;
+        }
+        match fs::read_to_string(&golden) {
+            Ok(expected) if expected == report => (),
+            Ok(expected) => failures.push(format!("{}:\n--- expected\n{}--- got\n{}", dump.display(), expected, report)),
+            Err(_) => failures.push(format!("{}: no golden file, got\n{}", dump.display(), report))
+        }
+    }
+    assert!(failures.is_empty(), "{}", failures.join("\n"));
+}
@@ -1,52 +1,169 @@
 extern crate termion;
 extern crate nix;
 use std::error;
-use std::io::{Read, Error as ioErr, ErrorKind as ioErrKind, Result as ioResult, Write};
-use std::os::unix::io::RawFd;
-use nix::unistd;
+use std::fmt;
+use std::fs::File;
+use std::io::{BufWriter, Read, Error as ioErr, Result as ioResult, Write};
+use std::os::unix::io::{AsRawFd, RawFd};
+use std::time::SystemTime;
+use crate::screen::GameScreen;
+use crate::ttyrec::TtyrecWriter;
+use nix::{ioctl_write_ptr_bad, libc};
+use nix::errno::Errno;
+use nix::sys::wait::{waitpid, WaitStatus};
+use nix::unistd::{self, ForkResult, Pid};
 use nix::pty::{forkpty, Winsize};
-use terminal_emulator::term::{SizeInfo, Term};
+use terminal_emulator::term::SizeInfo;
 use termion::{terminal_size, terminal_size_pixels};
 
 type Result<T> = std::result::Result<T, Box<dyn error::Error>>;
 
+// what can go wrong reading from the game, with the child going away
+// told apart from everything else
+#[derive(Debug)]
+pub enum PtyError {
+    // reads fail with EIO once nothing has the other end of the pty
+    // open, by then the child has exited and this is how
+    ChildExited(WaitStatus),
+    Io(nix::Error),
+    // writing the ttyrec of what was read
+    Record(ioErr)
+}
+
+impl fmt::Display for PtyError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            PtyError::ChildExited(status) => write!(f, "the game exited: {:?}", status),
+            PtyError::Io(e) => write!(f, "reading from the game's pty: {}", e),
+            PtyError::Record(e) => write!(f, "recording the game: {}", e)
+        }
+    }
+}
+
+impl error::Error for PtyError {}
+
+impl From<nix::Error> for PtyError {
+    fn from(e: nix::Error) -> Self {
+        PtyError::Io(e)
+    }
+}
+
+// the reader owns the pty master, the writer only borrows the fd, and
+// it's also the one keeping track of the child on the other end
 pub struct PtyReader {
     buffer: [u8; 4096],
     bounds: (usize, usize),
-    fd: RawFd
+    fd: RawFd,
+    child: Pid,
+    status: Option<WaitStatus>,
+    closed: bool,
+    recorder: Option<TtyrecWriter<BufWriter<File>>>
 }
 
 impl PtyReader {
-    pub fn new(fd: RawFd) -> Self {
+    pub fn new(fd: RawFd, child: Pid) -> Self {
         PtyReader {
             buffer: [0; 4096],
             bounds: (0, 0),
-            fd
+            fd,
+            child,
+            status: None,
+            closed: false,
+            recorder: None
         }
     }
 
+    // everything read from here on goes into `file` as ttyrec frames
+    pub fn record(&mut self, file: File) {
+        self.recorder = Some(TtyrecWriter::new(BufWriter::new(file)));
+    }
+
     pub fn len(&self) -> usize {
         self.bounds.1 - self.bounds.0
     }
 
-    pub fn raw_read(&mut self) -> ioResult<usize> {
+    // how the child exited, waiting for it if it hasn't been reaped yet
+    pub fn reap(&mut self) -> std::result::Result<WaitStatus, PtyError> {
+        if let Some(status) = self.status {
+            return Ok(status);
+        }
+        let status = loop {
+            match waitpid(self.child, None) {
+                Err(nix::Error::Sys(Errno::EINTR)) => continue,
+                result => break result?
+            }
+        };
+        self.status = Some(status);
+        Ok(status)
+    }
+
+    pub fn raw_read(&mut self) -> std::result::Result<usize, PtyError> {
         if self.bounds.0 == self.buffer.len() {
             self.bounds.0 = 0;
             self.bounds.1 = 0;
         }
-        let n = unistd::read(self.fd, &mut self.buffer[self.bounds.1 ..])
-            // the map_err() bit allows us to convert to the correct
-            // error type berfore applying ?
-            .map_err(|e| ioErr::new(ioErrKind::Other, e))?;
-        self.bounds.1 += n;
-        Ok(n)
+        loop {
+            match unistd::read(self.fd, &mut self.buffer[self.bounds.1 ..]) {
+                Ok(n) => {
+                    if let Some(ref mut recorder) = self.recorder {
+                        let data = &self.buffer[self.bounds.1 .. self.bounds.1 + n];
+                        recorder.write_frame(SystemTime::now(), data).map_err(PtyError::Record)?;
+                    }
+                    self.bounds.1 += n;
+                    return Ok(n);
+                },
+                Err(nix::Error::Sys(Errno::EINTR)) => continue,
+                Err(nix::Error::Sys(Errno::EIO)) => return Err(PtyError::ChildExited(self.reap()?)),
+                Err(e) => return Err(e.into())
+            }
+        }
+    }
+
+    // the next byte from the game, Ok(None) is a plain end of file
+    pub fn next_byte(&mut self) -> std::result::Result<Option<u8>, PtyError> {
+        if self.len() == 0 && self.raw_read()? == 0 {
+            return Ok(None);
+        }
+        let c = self.buffer[self.bounds.0];
+        self.bounds.0 += 1;
+        Ok(Some(c))
+    }
+
+    // hangs up on the child, which has NetHack save a game still in
+    // progress, and waits for it to go
+    pub fn close(mut self) -> std::result::Result<WaitStatus, PtyError> {
+        if let Some(ref mut recorder) = self.recorder {
+            recorder.flush().map_err(PtyError::Record)?;
+        }
+        self.closed = true;
+        unistd::close(self.fd)?;
+        self.reap()
+    }
+}
+
+impl AsRawFd for PtyReader {
+    fn as_raw_fd(&self) -> RawFd {
+        self.fd
+    }
+}
+
+impl Drop for PtyReader {
+    fn drop(&mut self) {
+        if !self.closed {
+            let _ = unistd::close(self.fd);
+        }
     }
 }
 
+// the child exiting is just the end of the stream here
 impl Read for PtyReader {
     fn read(&mut self, dest_buf: &mut [u8]) -> ioResult<usize> {
-        if self.len() == 0 && self.raw_read()? == 0 {
-            return Ok(0);
+        if self.len() == 0 {
+            match self.raw_read() {
+                Ok(0) | Err(PtyError::ChildExited(_)) => return Ok(0),
+                Ok(_) => (),
+                Err(e) => return Err(ioErr::other(e))
+            }
         }
 
         let len = if dest_buf.len() < self.len() {
@@ -55,30 +172,12 @@ impl Read for PtyReader {
             self.len()
         };
 
-        for i in 0 .. len {
-            dest_buf[i] = self.buffer[self.bounds.0 + i];
-        }
+        dest_buf[.. len].copy_from_slice(&self.buffer[self.bounds.0 .. self.bounds.0 + len]);
         self.bounds.0 += len;
         Ok(len)
     }
 }
 
-impl Iterator for PtyReader {
-    type Item = u8;
-
-    fn next(&mut self) -> Option<Self::Item> {
-        // using the iterator form means any I/O error will silently
-        // be treated as EOF and just return None
-        if self.len() == 0 && self.raw_read().unwrap_or(0) == 0 {
-            None
-        } else {
-            let c = self.buffer[self.bounds.0];
-            self.bounds.0 += 1;
-            Some(c)
-        }
-    }
-}
-
 pub struct PtyWriter {
     fd: RawFd
 }
@@ -91,20 +190,33 @@ impl PtyWriter {
     }
 }
 
+ioctl_write_ptr_bad!(set_winsize, libc::TIOCSWINSZ, Winsize);
+
+impl PtyWriter {
+    // the kernel passes the change on to NetHack as a SIGWINCH
+    pub fn resize(&self, win_size: &Winsize) -> Result<()> {
+        unsafe { set_winsize(self.fd, win_size)?; }
+        Ok(())
+    }
+}
+
 impl Write for PtyWriter {
     fn write(&mut self, buf: &[u8]) -> ioResult<usize> {
-        unistd::write(self.fd, &buf)
-            .map_err(|e| ioErr::new(ioErrKind::Other, e))
+        unistd::write(self.fd, buf)
+            .map_err(ioErr::other)
     }
 
+    // writes go straight to the pty, and fsync() on a terminal only
+    // fails with EINVAL
     fn flush(&mut self) -> ioResult<()> {
-        unistd::fsync(self.fd)
-            .map_err(|e| ioErr::new(ioErrKind::Other, e))
+        Ok(())
     }
 }
 
+// there's only ever one Parent alive, so the size difference doesn't matter
+#[allow(clippy::large_enum_variant)]
 pub enum TermFork {
-    Parent(PtyReader, PtyWriter, Term),
+    Parent(PtyReader, PtyWriter, GameScreen),
     Child
 }
 
@@ -119,6 +231,17 @@ pub fn get_winsize() -> Result<Winsize> {
     })
 }
 
+// a made up window for when there's no terminal to take the size from,
+// the pixel sizes only have to give sensible cell sizes
+pub fn virtual_winsize(rows: u16, cols: u16) -> Winsize {
+    Winsize {
+        ws_row: rows,
+        ws_col: cols,
+        ws_xpixel: cols * 8,
+        ws_ypixel: rows * 16
+    }
+}
+
 pub fn sizeinfo_from(win_size: Winsize) -> SizeInfo {
     let width = win_size.ws_xpixel as f32;
     let height = win_size.ws_ypixel as f32;
@@ -136,17 +259,16 @@ pub fn sizeinfo_from(win_size: Winsize) -> SizeInfo {
     }
 }
 
-pub fn fork_terminal() -> Result<TermFork> {
-    let win_size = get_winsize()?;
+pub fn fork_terminal(win_size: Winsize) -> Result<TermFork> {
     let fork = forkpty(Some(&win_size), None)?;
 
-    if fork.fork_result.is_parent() {
+    if let ForkResult::Parent { child } = fork.fork_result {
         let raw_fd = fork.master;
 
         let size_info = sizeinfo_from(win_size);
-        let emulator = Term::new(size_info);
+        let screen = GameScreen::new(size_info);
 
-        Ok(TermFork::Parent(PtyReader::new(raw_fd), PtyWriter::new(raw_fd), emulator))
+        Ok(TermFork::Parent(PtyReader::new(raw_fd, child), PtyWriter::new(raw_fd), screen))
     } else {
         Ok(TermFork::Child)
     }
@@ -0,0 +1,103 @@
+use std::error;
+use std::io::{ErrorKind, Read, Result as ioResult, Write};
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+type Result<T> = std::result::Result<T, Box<dyn error::Error>>;
+
+// a ttyrec file is a run of frames, each one a 12 byte header of three
+// little endian u32s (seconds, microseconds, length) and then `length`
+// bytes of terminal output
+pub struct Frame {
+    pub time: Duration,
+    pub data: Vec<u8>
+}
+
+pub struct TtyrecReader<R: Read> {
+    inner: R
+}
+
+impl<R: Read> TtyrecReader<R> {
+    pub fn new(inner: R) -> Self {
+        TtyrecReader { inner }
+    }
+
+    // None at a clean end of file, a header cut short is an error
+    pub fn read_frame(&mut self) -> Result<Option<Frame>> {
+        let mut header = [0u8; 12];
+        match self.inner.read_exact(&mut header[..1]) {
+            Ok(()) => (),
+            Err(e) if e.kind() == ErrorKind::UnexpectedEof => return Ok(None),
+            Err(e) => return Err(e.into())
+        }
+        self.inner.read_exact(&mut header[1..])?;
+        let field = |i: usize| u32::from_le_bytes([header[i], header[i+1], header[i+2], header[i+3]]);
+        let time = Duration::from_secs(field(0) as u64) + Duration::from_micros(field(4) as u64);
+        let mut data = vec![0u8; field(8) as usize];
+        self.inner.read_exact(&mut data)?;
+        Ok(Some(Frame { time, data }))
+    }
+}
+
+pub struct TtyrecWriter<W: Write> {
+    inner: W
+}
+
+impl<W: Write> TtyrecWriter<W> {
+    pub fn new(inner: W) -> Self {
+        TtyrecWriter { inner }
+    }
+
+    pub fn write_frame(&mut self, time: SystemTime, data: &[u8]) -> ioResult<()> {
+        let time = time.duration_since(UNIX_EPOCH).unwrap_or_default();
+        let mut header = [0u8; 12];
+        header[0..4].copy_from_slice(&(time.as_secs() as u32).to_le_bytes());
+        header[4..8].copy_from_slice(&time.subsec_micros().to_le_bytes());
+        header[8..12].copy_from_slice(&(data.len() as u32).to_le_bytes());
+        self.inner.write_all(&header)?;
+        self.inner.write_all(data)
+    }
+
+    pub fn flush(&mut self) -> ioResult<()> {
+        self.inner.flush()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn frames_round_trip() {
+        let first = UNIX_EPOCH + Duration::new(0x01020304, 5_006_000);
+        let second = first + Duration::from_millis(1500);
+        let mut file = Vec::new();
+        {
+            let mut writer = TtyrecWriter::new(&mut file);
+            writer.write_frame(first, b"\x1b[Hhello").unwrap();
+            writer.write_frame(second, b"").unwrap();
+            writer.flush().unwrap();
+        }
+
+        // seconds, microseconds and length, each a little endian u32
+        assert_eq!(file[..12], [0x04, 0x03, 0x02, 0x01, 0x8e, 0x13, 0x00, 0x00, 0x08, 0x00, 0x00, 0x00]);
+        assert_eq!(&file[12..20], b"\x1b[Hhello");
+        assert_eq!(file.len(), 20 + 12);
+
+        let mut reader = TtyrecReader::new(file.as_slice());
+        let frame = reader.read_frame().unwrap().unwrap();
+        assert_eq!(frame.time, Duration::new(0x01020304, 5_006_000));
+        assert_eq!(frame.data, b"\x1b[Hhello");
+        let frame = reader.read_frame().unwrap().unwrap();
+        assert_eq!(frame.time, Duration::new(0x01020305, 505_006_000));
+        assert!(frame.data.is_empty());
+        assert!(reader.read_frame().unwrap().is_none());
+    }
+
+    #[test]
+    fn a_cut_short_frame_is_an_error() {
+        let mut reader = TtyrecReader::new(&[1u8, 0, 0, 0, 0, 0][..]);
+        assert!(reader.read_frame().is_err());
+        let mut reader = TtyrecReader::new(&[0u8, 0, 0, 0, 0, 0, 0, 0, 4, 0, 0, 0, b'a'][..]);
+        assert!(reader.read_frame().is_err());
+    }
+}
@@ -0,0 +1,100 @@
+// runs titm headless against fakehack, the scripted stand-in for
+// NetHack, through a real pty and the whole quit and restart flow
+use std::env;
+use std::fs;
+use std::path::PathBuf;
+use std::process::{Command, Stdio};
+use std::thread;
+use std::time::{Duration, Instant};
+
+// long enough for a few games at the frame detector's pace
+const TIMEOUT: Duration = Duration::from_secs(60);
+
+const WANDS: &str = "class = \"wand\"";
+
+struct Run {
+    success: bool,
+    stdout: String,
+    runs: Vec<String>
+}
+
+fn scratch_dir(name: &str) -> PathBuf {
+    let dir = env::temp_dir().join(format!("titm-e2e-{}-{}", name, std::process::id()));
+    let _ = fs::remove_dir_all(&dir);
+    fs::create_dir_all(&dir).unwrap();
+    dir
+}
+
+// `target` is the body of the one [[target]] table
+fn run_titm(name: &str, target: &str, game_args: &[&str], max_runs: u32) -> Run {
+    let dir = scratch_dir(name);
+    let config = dir.join("titm.toml");
+    fs::write(&config, format!(
+        "[game]\ncommand = {:?}\nargs = {:?}\n\n[[target]]\n{}\n",
+        env!("CARGO_BIN_EXE_fakehack"), game_args, target
+    )).unwrap();
+    let runs = dir.join("runs.log");
+
+    let mut child = Command::new(env!("CARGO_BIN_EXE_titm"))
+        .arg("--config").arg(&config)
+        .arg("--runs").arg(&runs)
+        .args(["run", "--headless", "--no-record", "--max-runs", &max_runs.to_string()])
+        .stdin(Stdio::null())
+        .stdout(Stdio::piped())
+        .stderr(Stdio::inherit())
+        .spawn()
+        .unwrap();
+    let started = Instant::now();
+    while child.try_wait().unwrap().is_none() {
+        if started.elapsed() > TIMEOUT {
+            let _ = child.kill();
+            panic!("titm still running after {:?}", TIMEOUT);
+        }
+        thread::sleep(Duration::from_millis(50));
+    }
+    let output = child.wait_with_output().unwrap();
+
+    let runs = fs::read_to_string(&runs).unwrap_or_default()
+        .lines()
+        .filter_map(|line| line.split_whitespace().nth(2).map(str::to_string))
+        .collect();
+    let _ = fs::remove_dir_all(&dir);
+    Run {
+        success: output.status.success(),
+        stdout: String::from_utf8_lossy(&output.stdout).into_owned(),
+        runs
+    }
+}
+
+#[test]
+fn quits_and_restarts_when_nothing_is_in_view() {
+    let run = run_titm("empty", WANDS, &["--level", "empty"], 2);
+    assert!(run.success);
+    assert_eq!(run.stdout.trim(), "nothing found in 2 games");
+    assert_eq!(run.runs, ["rerolled", "rerolled"]);
+}
+
+#[test]
+fn travels_to_and_picks_up_a_wand() {
+    let run = run_titm("wand", WANDS, &["--level", "wand"], 3);
+    assert!(run.success);
+    assert_eq!(run.stdout.trim(), "found a target on run 1");
+    assert_eq!(run.runs, ["found"]);
+}
+
+#[test]
+fn a_starting_wand_is_no_find() {
+    let run = run_titm("starting-wand", WANDS, &["--level", "wand", "--wand", "--levitating"], 1);
+    assert!(run.success);
+    assert_eq!(run.stdout.trim(), "nothing found in 1 games");
+    assert_eq!(run.runs, ["rerolled"]);
+}
+
+#[test]
+fn wands_of_the_wrong_appearance_are_looked_at_once() {
+    let run = run_titm("wrong-wands", "class = \"wand\"\nappearance = \"oak\"",
+                       &["--level", "wands", "--appearance", "balsa"], 1);
+    assert!(run.success);
+    assert_eq!(run.stdout.trim(), "nothing found in 1 games");
+    assert_eq!(run.runs, ["rerolled"]);
+}